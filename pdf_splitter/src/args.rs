@@ -1,16 +1,39 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use regex::Regex;
 
+use crate::{
+    merge::MergeInput,
+    source::{parse_size, Ranges},
+    splitter::Depth,
+};
+
 #[derive(Parser)]
 #[clap(
     name = "pdf_splitter",
     version = env!("CARGO_PKG_VERSION"),
     author = "Connor Slade <connor@connorcode.com>",
     about = "Split PDF files by section",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
 )]
 #[rustfmt::skip]
+pub struct Cli {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+    #[clap(flatten)]
+    pub split: Option<Args>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Combine PDFs, or pages from them, into one file with a bookmark for each.
+    Merge(MergeArgs),
+}
+
+#[derive(Parser)]
+#[rustfmt::skip]
 pub struct Args {
     // == Basic Args ==
     /// The input PDF file.
@@ -18,29 +41,77 @@ pub struct Args {
     /// The output directory to write the split PDF files to.
     pub output_dir: PathBuf,
     /// Regex to match the section name.
+    #[clap(default_value = ".*", conflicts_with = "config")]
     pub should_split: Regex,
     /// Regex to capture the section name.
+    #[clap(default_value = "(.*)", conflicts_with = "config")]
     pub rename_captures: Regex,
     /// Formatter used with `rename_captures` to rename the output files.
+    #[clap(default_value = "$1", conflicts_with = "config")]
     pub rename_format: String,
 
     // == Flags ==
     /// Dry run, don't save any files.
     #[clap(long, short)]
     pub dry_run: bool,
+    /// Print the dry run as JSON, in the same format as `manifest.json`.
+    #[clap(long, requires = "dry_run")]
+    pub json: bool,
     /// Don't replace spaces and colons in the section name.
     #[clap(long)]
     pub allow_unchecked: bool,
+    /// Remove links to pages in other sections, instead of linking to the file they were split into.
+    #[clap(long)]
+    pub remove_outside_links: bool,
 
     // == Optional Args ==
+    /// Password to decrypt the input with, either the user or the owner password. RC4 and AES are supported.
+    #[clap(long, short)]
+    pub password: Option<String>,
+    /// A TOML job file with rules for naming and adjusting sections, instead of the regex arguments.
+    #[clap(long, short)]
+    pub config: Option<PathBuf>,
     /// The name of the first section.
     #[clap(long, short, default_value = "Title")]
     pub start_name: String,
     /// The name of the last section.
     #[clap(long, short, default_value = "End")]
     pub end_name: String,
-    /// The depth of heading to search through.
+    /// The depth of heading to search through, 0 by default or every heading with `--nested`.
     /// Setting to -1 will include all headings.
-    #[clap(long, default_value_t = {0})]
-    pub depth: usize
+    #[clap(long, allow_hyphen_values = true)]
+    pub depth: Option<Depth>,
+    /// Split into a directory for each heading with sub-sections, down to `depth` or all the way without it.
+    #[clap(long, short)]
+    pub nested: bool,
+
+    // == Split Strategies ==
+    // For documents without an outline
+    /// Split at page ranges instead of the outline, like `1-4:Intro,5-20:Chapter 1`.
+    #[clap(long, group = "strategy", conflicts_with = "nested")]
+    pub ranges: Option<Ranges>,
+    /// Split into parts with this many pages.
+    #[clap(long, group = "strategy", conflicts_with = "nested")]
+    pub pages: Option<usize>,
+    /// Split into parts of about this size, like `5M`.
+    #[clap(long, group = "strategy", conflicts_with = "nested", value_parser = parse_size)]
+    pub size: Option<u64>,
+    /// Start a new section on every page with text matching this regex, named after the match.
+    #[clap(long, group = "strategy", conflicts_with = "nested")]
+    pub page_regex: Option<Regex>,
+}
+
+#[derive(Parser)]
+#[rustfmt::skip]
+pub struct MergeArgs {
+    /// The PDFs to combine, in order.
+    /// Add page ranges after a colon to only take some pages, like `book.pdf:5-20:Chapter 2,30-34`.
+    #[clap(required = true)]
+    pub inputs: Vec<MergeInput>,
+    /// The PDF file to write.
+    #[clap(long, short)]
+    pub output: PathBuf,
+    /// Password to decrypt the inputs with, either the user or the owner password. RC4 and AES are supported.
+    #[clap(long, short)]
+    pub password: Option<String>,
 }
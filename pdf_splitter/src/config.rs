@@ -0,0 +1,157 @@
+use std::{borrow::Cow, fs, path::Path, path::PathBuf, sync::Arc};
+
+use anyhow::Context;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{
+    args::Args,
+    splitter::{clean_name, Adjust, Section, Special, Splitter},
+};
+
+/// A job file with rules for which sections to split out and what to do with them.
+/// Each section uses the first rule that matches its name, and sections no rule matches are skipped.
+///
+/// ```toml
+/// [[rule]]
+/// match = '^\d+ '
+/// captures = '^(\d+) (.*)'
+/// rename = 'Chapter $1 - $2'
+/// dir = 'chapters'
+/// after = 1
+/// merge_shorter_than = 2
+///
+/// [[rule]]
+/// match = '^Appendix'
+/// skip = true
+/// ```
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JobFile {
+    #[serde(default)]
+    rule: Vec<RawRule>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRule {
+    /// Regex to match the section name.
+    #[serde(rename = "match")]
+    matches: String,
+    /// Regex to capture parts of the section name, defaults to `match`.
+    captures: Option<String>,
+    /// Formatter used with `captures` to rename the output file, the name is left as is without one.
+    rename: Option<String>,
+    /// Directory in the output directory to put the file in.
+    dir: Option<PathBuf>,
+    #[serde(default)]
+    before: usize,
+    #[serde(default)]
+    after: usize,
+    #[serde(default)]
+    merge_shorter_than: usize,
+    /// Leave out the sections this rule matches.
+    #[serde(default)]
+    skip: bool,
+}
+
+pub struct Rule {
+    matches: Regex,
+    captures: Regex,
+    rename: Option<String>,
+    dir: Option<PathBuf>,
+    adjust: Adjust,
+    skip: bool,
+}
+
+/// Names and adjusts sections with the rules in a job file.
+pub struct ConfigSplitter {
+    args: Arc<Args>,
+    rules: Vec<Rule>,
+}
+
+impl ConfigSplitter {
+    pub fn load(path: &Path, args: Arc<Args>) -> anyhow::Result<Self> {
+        let file = fs::read_to_string(path).context("Reading job file")?;
+        let rules = parse(&file).context("Parsing job file")?;
+        Ok(Self::new(rules, args))
+    }
+
+    pub fn new(rules: Vec<Rule>, args: Arc<Args>) -> Self {
+        Self { args, rules }
+    }
+
+    fn rule(&self, section: &Section) -> Option<&Rule> {
+        self.rules
+            .iter()
+            .find(|x| x.matches.is_match(&section.name))
+    }
+}
+
+pub fn parse(file: &str) -> anyhow::Result<Vec<Rule>> {
+    let file = toml::from_str::<JobFile>(file)?;
+
+    let mut rules = Vec::new();
+    for (i, rule) in file.rule.into_iter().enumerate() {
+        let context = || format!("Rule {}", i + 1);
+        let matches = Regex::new(&rule.matches).with_context(context)?;
+        let captures = match rule.captures {
+            Some(captures) => Regex::new(&captures).with_context(context)?,
+            None => matches.clone(),
+        };
+
+        rules.push(Rule {
+            matches,
+            captures,
+            rename: rule.rename,
+            dir: rule.dir,
+            adjust: Adjust {
+                before: rule.before,
+                after: rule.after,
+                merge_shorter_than: rule.merge_shorter_than,
+            },
+            skip: rule.skip,
+        });
+    }
+
+    Ok(rules)
+}
+
+impl Splitter for ConfigSplitter {
+    fn name<'a>(&self, section: &'a Section) -> Cow<'a, str> {
+        match section.special {
+            Special::StartSlack => return Cow::Owned(self.args.start_name.to_owned()),
+            Special::EndSlack => return Cow::Owned(self.args.end_name.to_owned()),
+            _ => {}
+        }
+
+        let name = match self.rule(section) {
+            Some(Rule {
+                rename: Some(rename),
+                captures,
+                ..
+            }) => captures.replace_all(&section.name, rename),
+            _ => Cow::Borrowed(section.name.as_str()),
+        };
+
+        match self.args.allow_unchecked {
+            true => name,
+            false => Cow::Owned(clean_name(&name)),
+        }
+    }
+
+    fn should_split(&self, section: &Section) -> bool {
+        self.rule(section).is_some_and(|x| !x.skip)
+    }
+
+    fn adjust(&self, section: &Section) -> Adjust {
+        self.rule(section).map(|x| x.adjust).unwrap_or_default()
+    }
+
+    fn dir(&self, section: &Section) -> Option<PathBuf> {
+        match section.special {
+            Special::None => self.rule(section).and_then(|x| x.dir.to_owned()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{anyhow, bail};
+use lopdf::{encryption::PasswordAlgorithm, Document, Object, ObjectId};
+use md5::{Digest, Md5};
+
+use crate::labels::{self, Label};
+
+/// The document being split, with an index of its pages.
+/// Jobs only ever read from it, so one is shared between all of them.
+pub struct Input {
+    pub doc: Document,
+    /// The ID of every page, in order.
+    pub pages: Vec<ObjectId>,
+    /// The index of each page from its ID.
+    pub page_numbers: HashMap<ObjectId, usize>,
+    /// The document's page labels, if it has any.
+    pub labels: Vec<Label>,
+}
+
+impl Input {
+    pub fn new(doc: Document) -> Self {
+        let pages = doc.page_iter().collect::<Vec<_>>();
+        let page_numbers = pages.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+        let labels = labels::read(&doc);
+
+        Self {
+            doc,
+            pages,
+            page_numbers,
+            labels,
+        }
+    }
+}
+
+/// The padding appended to passwords shorter than 32 bytes by the standard security handler.
+const PADDING: [u8; 32] = [
+    0x28, 0xBF, 0x4E, 0x5E, 0x4E, 0x75, 0x8A, 0x41, 0x64, 0x00, 0x4E, 0x56, 0xFF, 0xFA, 0x01, 0x08,
+    0x2E, 0x2E, 0x00, 0xB6, 0xD0, 0x68, 0x3E, 0x80, 0x2F, 0x0C, 0xA9, 0xFE, 0x64, 0x53, 0x69, 0x7A,
+];
+
+/// Loads a document, decrypting it if it's encrypted.
+/// Lots of documents are encrypted with an empty password just to set permissions, so that's tried first.
+/// The password can be either the user or the owner password, with RC4 or AES encryption.
+pub fn load(path: &Path, password: Option<&str>) -> anyhow::Result<Document> {
+    let doc = Document::load(path)?;
+    // Documents that couldn't be decrypted are loaded without any objects, but keep their `Encrypt`
+    if !doc.trailer.has(b"Encrypt") {
+        return Ok(doc);
+    }
+    let Some(password) = password else {
+        bail!("Document is encrypted, pass its password with --password");
+    };
+
+    let password = user_password(&doc, password).unwrap_or_else(|| password.to_owned());
+    Document::load_with_password(path, &password).map_err(|e| match e {
+        lopdf::Error::InvalidPassword => anyhow!("Wrong password"),
+        e => anyhow::Error::new(e).context("Decrypting document"),
+    })
+}
+
+/// The user password of a document encrypted with RC4 or AES-128 (revisions 2 to 4), if `owner` is its owner password.
+/// lopdf derives the key from any password as if it was the user password, which only holds for AES-256,
+/// but the user password is stored in `O`, encrypted with a key derived from the owner password.
+fn user_password(doc: &Document, owner: &str) -> Option<String> {
+    let encrypt = doc.get_encrypted().ok()?;
+    let revision = encrypt.get(b"R").and_then(Object::as_i64).ok()?;
+    if !(2..=4).contains(&revision)
+        || doc.authenticate_user_password(owner).is_ok()
+        || doc.authenticate_owner_password(owner).is_err()
+    {
+        return None;
+    }
+
+    let owner = PasswordAlgorithm::try_from(doc)
+        .ok()?
+        .sanitize_password(owner)
+        .ok()?;
+    let owner = &owner[..owner.len().min(32)];
+    let mut hash = Md5::new()
+        .chain_update(owner)
+        .chain_update(&PADDING[..32 - owner.len()])
+        .finalize();
+    let length = match revision {
+        2 => 5,
+        _ => {
+            for _ in 0..50 {
+                hash = Md5::digest(hash);
+            }
+            let bits = encrypt
+                .get(b"Length")
+                .and_then(Object::as_i64)
+                .unwrap_or(40);
+            (bits as usize / 8).clamp(5, 16)
+        }
+    };
+
+    // Revision 3 and up encrypt it 20 times, with the key XORed with 19 down to 0
+    let rounds = if revision == 2 { 0 } else { 19 };
+    let mut password = encrypt.get(b"O").and_then(Object::as_str).ok()?.to_vec();
+    for i in (0..=rounds).rev() {
+        let key = hash[..length].iter().map(|x| x ^ i).collect::<Vec<_>>();
+        password = rc4(&key, &password);
+    }
+
+    // The user password is padded the same way, and PDFDocEncoding matches Latin-1 for anything typed
+    let end = (0..=password.len()).find(|&i| PADDING.starts_with(&password[i..]))?;
+    Some(password[..end].iter().map(|&x| x as char).collect())
+}
+
+/// Encrypts or decrypts `data` with RC4, which lopdf doesn't expose.
+fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut state: [u8; 256] = std::array::from_fn(|i| i as u8);
+    let mut j = 0u8;
+    for i in 0..256 {
+        j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+        state.swap(i, j as usize);
+    }
+
+    let (mut i, mut j) = (0u8, 0u8);
+    data.iter()
+        .map(|byte| {
+            i = i.wrapping_add(1);
+            j = j.wrapping_add(state[i as usize]);
+            state.swap(i as usize, j as usize);
+            byte ^ state[state[i as usize].wrapping_add(state[j as usize]) as usize]
+        })
+        .collect()
+}
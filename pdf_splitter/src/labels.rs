@@ -0,0 +1,103 @@
+use std::{collections::HashSet, ops::Range};
+
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId};
+
+/// A page label from the `PageLabels` number tree, and the index of the first page it's used for.
+pub type Label = (usize, Dictionary);
+
+/// Reads the document's page labels, sorted by page.
+/// Any references in the label dictionaries are resolved, so they can be moved between documents as is.
+pub fn read(doc: &Document) -> Vec<Label> {
+    let mut labels = Vec::new();
+    if let Ok(tree) = doc
+        .catalog()
+        .and_then(|x| x.get(b"PageLabels"))
+        .and_then(|x| doc.dereference(x))
+        .and_then(|x| x.1.as_dict())
+    {
+        number_tree(doc, tree, &mut labels, &mut HashSet::new());
+    }
+
+    labels.sort_by_key(|x| x.0);
+    labels
+}
+
+fn number_tree(
+    doc: &Document,
+    node: &Dictionary,
+    out: &mut Vec<Label>,
+    seen: &mut HashSet<ObjectId>,
+) {
+    if let Ok(nums) = node.get(b"Nums").and_then(Object::as_array) {
+        for pair in nums.chunks_exact(2) {
+            let Ok(page) = pair[0].as_i64() else {
+                continue;
+            };
+            let Ok(Object::Dictionary(label)) = doc.dereference(&pair[1]).map(|x| x.1) else {
+                continue;
+            };
+
+            let label = label
+                .iter()
+                .filter_map(|(key, value)| Some((key.to_owned(), doc.dereference(value).ok()?.1)))
+                .map(|(key, value)| (key, value.to_owned()))
+                .collect();
+            out.push((page.max(0) as usize, label));
+        }
+    }
+
+    let Ok(kids) = node.get(b"Kids").and_then(Object::as_array) else {
+        return;
+    };
+    for kid in kids.iter().filter_map(|x| x.as_reference().ok()) {
+        if seen.insert(kid) {
+            if let Ok(kid) = doc.get_dictionary(kid) {
+                number_tree(doc, kid, out, seen);
+            }
+        }
+    }
+}
+
+/// The labels for some of the pages, starting from the first one.
+/// The label the pages start in carries on from the number it was at, so every page keeps its printed number.
+pub fn section(labels: &[Label], pages: &Range<usize>) -> Vec<Label> {
+    if labels.is_empty() {
+        return Vec::new();
+    }
+
+    let first = match labels.iter().rev().find(|x| x.0 <= pages.start) {
+        Some((start, label)) => {
+            let mut label = label.to_owned();
+            let number = label.get(b"St").and_then(Object::as_i64).unwrap_or(1);
+            label.set("St", number + (pages.start - start) as i64);
+            label
+        }
+        // Pages before the first label are shown with their page number
+        None => dictionary! {
+            "S" => "D",
+            "St" => pages.start as i64 + 1,
+        },
+    };
+
+    let mut out = vec![(0, first)];
+    out.extend(
+        labels
+            .iter()
+            .filter(|x| x.0 > pages.start && x.0 < pages.end)
+            .map(|(page, label)| (page - pages.start, label.to_owned())),
+    );
+    out
+}
+
+/// Makes a `PageLabels` number tree, or `None` if there aren't any labels.
+pub fn tree(labels: Vec<Label>) -> Option<Dictionary> {
+    if labels.is_empty() {
+        return None;
+    }
+
+    let nums = labels
+        .into_iter()
+        .flat_map(|(page, label)| [Object::Integer(page as i64), Object::Dictionary(label)])
+        .collect::<Vec<_>>();
+    Some(dictionary! { "Nums" => nums })
+}
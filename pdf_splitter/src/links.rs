@@ -0,0 +1,156 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+    path::{Component, Path, PathBuf},
+};
+
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, StringFormat};
+
+use crate::{input::Input, outline, splitter::SplitterJob};
+
+/// What's needed to fix up links and form fields in the split documents.
+/// It's made once and shared between every job.
+pub struct Links {
+    named: HashMap<Vec<u8>, Vec<Object>>,
+    /// The file each range of pages is split into, or `None` if links leaving a section should be removed.
+    outputs: Option<Vec<(Range<usize>, PathBuf)>>,
+}
+
+impl Links {
+    pub fn new(doc: &Document, outputs: Option<Vec<(Range<usize>, PathBuf)>>) -> Self {
+        let named = match doc.catalog() {
+            Ok(catalog) => outline::named_destinations(doc, catalog),
+            Err(_) => HashMap::new(),
+        };
+
+        Self { named, outputs }
+    }
+
+    /// Rewrites the links in a page's `Annots` for an output with the given pages.
+    /// Links to a page in the output still point to the old page, which is swapped for the new one when it's copied.
+    /// Links to other pages point to the file they were split into, or are removed.
+    /// Other annotations are left alone.
+    pub fn annots(&self, input: &Input, annots: &Object, job: &SplitterJob) -> Vec<Object> {
+        let Ok((_, Object::Array(annots))) = input.doc.dereference(annots) else {
+            return Vec::new();
+        };
+
+        annots
+            .iter()
+            .filter_map(|x| self.annot(input, x, job))
+            .collect()
+    }
+
+    fn annot(&self, input: &Input, annot: &Object, job: &SplitterJob) -> Option<Object> {
+        let doc = &input.doc;
+        let Ok((_, Object::Dictionary(dict))) = doc.dereference(annot) else {
+            return None;
+        };
+
+        let is_link = dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Link");
+        // URIs and other actions work the same in any file
+        let Some(dest) = outline::target(doc, dict).filter(|_| is_link) else {
+            return Some(annot.to_owned());
+        };
+
+        // Links to missing pages are removed
+        let dest = outline::destination(doc, dest, &self.named)?;
+        let page = *input
+            .page_numbers
+            .get(&dest.first()?.as_reference().ok()?)?;
+
+        let mut dict = dict.to_owned();
+        dict.remove(b"A");
+        dict.remove(b"Dest");
+        if job.pages.contains(&page) {
+            dict.set("Dest", dest);
+        } else {
+            let (range, file) = self
+                .outputs
+                .as_ref()?
+                .iter()
+                .find(|(range, _)| range.contains(&page))?;
+            let uri = format!(
+                "{}#page={}",
+                relative(&job.filename, file),
+                page - range.start + 1
+            );
+            dict.set(
+                "A",
+                dictionary! {
+                    "S" => "URI",
+                    "URI" => Object::String(uri.into_bytes(), StringFormat::Literal),
+                },
+            );
+        }
+
+        Some(Object::Dictionary(dict))
+    }
+
+    /// The interactive form with only the fields that have a widget in `annots`.
+    /// Returns `None` if none of the fields are left.
+    pub fn form(
+        &self,
+        input: &Input,
+        form: &Object,
+        annots: &HashSet<ObjectId>,
+    ) -> Option<Dictionary> {
+        let doc = &input.doc;
+        let mut form = doc.dereference(form).ok()?.1.as_dict().ok()?.to_owned();
+        let fields = form
+            .get(b"Fields")
+            .and_then(|x| doc.dereference(x))
+            .and_then(|x| x.1.as_array())
+            .ok()?
+            .iter()
+            .filter(|x| has_widget(doc, x, annots, &mut HashSet::new()))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if fields.is_empty() {
+            return None;
+        }
+
+        form.set("Fields", fields);
+        Some(form)
+    }
+}
+
+/// If the field or any of its kids is one of the annotations.
+fn has_widget(
+    doc: &Document,
+    field: &Object,
+    annots: &HashSet<ObjectId>,
+    seen: &mut HashSet<ObjectId>,
+) -> bool {
+    if let Ok(id) = field.as_reference() {
+        if annots.contains(&id) {
+            return true;
+        }
+        if !seen.insert(id) {
+            return false;
+        }
+    }
+
+    let Ok((_, Object::Dictionary(field))) = doc.dereference(field) else {
+        return false;
+    };
+    field
+        .get(b"Kids")
+        .and_then(Object::as_array)
+        .is_ok_and(|kids| kids.iter().any(|x| has_widget(doc, x, annots, seen)))
+}
+
+/// The path to `to` from the directory `from` is in, both being relative to the output directory.
+fn relative(from: &Path, to: &Path) -> String {
+    let up = from.parent().map_or(0, |x| x.components().count());
+    let to = to.components().filter_map(|x| match x {
+        Component::Normal(x) => Some(x.to_string_lossy()),
+        _ => None,
+    });
+
+    std::iter::repeat_n("..".into(), up)
+        .chain(to)
+        .collect::<Vec<_>>()
+        .join("/")
+}
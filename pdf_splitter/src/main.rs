@@ -1,29 +1,65 @@
 use std::{borrow::Cow, fs, sync::Arc};
 
 use anyhow::Context;
-use args::Args;
+use args::{Args, Cli, Command};
 use clap::Parser;
-use indicatif::ParallelProgressIterator;
-use lopdf::Document;
+use config::ConfigSplitter;
+use indicatif::{ParallelProgressIterator, ProgressBar};
+use input::Input;
+use links::Links;
+use manifest::Manifest;
 use rayon::prelude::{ParallelBridge, ParallelIterator};
 
 mod args;
+mod config;
+mod input;
+mod labels;
+mod links;
+mod manifest;
+mod merge;
+mod outline;
 mod pdf;
+mod prune;
+mod source;
 mod splitter;
-use splitter::{jobs, Section, Special, Splitter};
+#[cfg(test)]
+mod test;
+use source::{Chunks, PageText, Size, Source};
+use splitter::{clean_name, jobs, nested_jobs, Depth, Section, Special, Splitter};
 
 const PRODUCER: &[u8] = b"pdf_splitter by Connor Slade [https://github.com/Basicprogrammer10/misc/tree/main/pdf_splitter]";
 
 fn main() -> anyhow::Result<()> {
-    let args = Arc::new(Args::parse());
+    let cli = Cli::parse();
+    let args = match cli.command {
+        Some(Command::Merge(args)) => return merge::merge(&args),
+        None => Arc::new(cli.split.context("Missing arguments")?),
+    };
 
-    println!(
-        "[*] Loading Document `{}`",
-        args.input_file.to_string_lossy()
-    );
-    let doc = Arc::new(Document::load(&args.input_file).context("Loading Document")?);
-    let splitter = ArgSplitter { args: args.clone() };
-    let jobs = jobs(doc.clone(), &splitter, args.depth)?;
+    // Only print the JSON so it can be piped into other tools
+    if !args.json {
+        println!(
+            "[*] Loading Document `{}`",
+            args.input_file.to_string_lossy()
+        );
+    }
+    let doc =
+        input::load(&args.input_file, args.password.as_deref()).context("Loading Document")?;
+    let splitter: Box<dyn Splitter> = match &args.config {
+        Some(path) => Box::new(ConfigSplitter::load(path, args.clone())?),
+        None => Box::new(ArgSplitter { args: args.clone() }),
+    };
+    let splitter = splitter.as_ref();
+    let jobs = match (source(&args), args.nested) {
+        (Some(source), _) => source::jobs(&doc, source.as_ref(), splitter)?,
+        (None, true) => nested_jobs(&doc, splitter, args.depth.unwrap_or(Depth::All))?,
+        (None, false) => jobs(&doc, splitter, args.depth.unwrap_or(Depth::Level(0)))?,
+    };
+
+    if args.json {
+        println!("{}", Manifest::new(&args.input_file, &jobs).to_json());
+        return Ok(());
+    }
 
     if args.dry_run {
         println!("[*] Dry run, not saving files");
@@ -44,15 +80,58 @@ fn main() -> anyhow::Result<()> {
     );
     fs::create_dir_all(&args.output_dir).context("Creating folder")?;
 
-    let job_count = jobs.len() as u64;
+    let outputs = (!args.remove_outside_links).then(|| {
+        jobs.iter()
+            .map(|x| (x.pages.clone(), x.filename.clone()))
+            .collect()
+    });
+    let links = Links::new(&doc, outputs);
+
+    let input = Input::new(doc);
+    let manifest = Manifest::new(&args.input_file, &jobs);
+
+    let progress = ProgressBar::new(jobs.len() as u64);
     jobs.into_iter()
         .par_bridge()
-        .progress_count(job_count)
-        .for_each(|x| pdf::split(x, doc.clone(), &args.output_dir));
+        .progress_with(progress.clone())
+        .try_for_each(|x| {
+            let filename = x.filename.to_owned();
+            let stats = pdf::split(x, &input, &links, &args.output_dir)
+                .with_context(|| format!("Splitting `{}`", filename.to_string_lossy()))?;
+            progress.suspend(|| {
+                println!(
+                    " | {}: {} objects, {:.0}% cache hits, {} unused resources removed",
+                    filename.to_string_lossy(),
+                    stats.copied,
+                    stats.hit_rate() * 100.0,
+                    stats.pruned
+                )
+            });
+            anyhow::Ok(())
+        })?;
+
+    println!("[*] Writing manifest");
+    manifest.save(&args.output_dir)?;
 
     Ok(())
 }
 
+/// The split strategy picked in the args, if it isn't to use the outline.
+fn source(args: &Args) -> Option<Box<dyn Source>> {
+    if let Some(ranges) = &args.ranges {
+        return Some(Box::new(ranges.to_owned()));
+    }
+    if let Some(pages) = args.pages {
+        return Some(Box::new(Chunks(pages)));
+    }
+    if let Some(size) = args.size {
+        return Some(Box::new(Size(size)));
+    }
+
+    let page_regex = args.page_regex.to_owned()?;
+    Some(Box::new(PageText(page_regex)))
+}
+
 struct ArgSplitter {
     args: Arc<Args>,
 }
@@ -71,7 +150,7 @@ impl Splitter for ArgSplitter {
             .replace_all(&section.name, &self.args.rename_format);
 
         if !self.args.allow_unchecked {
-            name = Cow::Owned(name.replace(' ', "_").replace(':', ""));
+            name = Cow::Owned(clean_name(&name));
         }
 
         name
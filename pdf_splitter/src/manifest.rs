@@ -0,0 +1,54 @@
+use std::{fs, path::Path};
+
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::splitter::SplitterJob;
+
+/// Every file a split makes, written to `manifest.json` so other tools don't have to guess from the names.
+#[derive(Serialize)]
+pub struct Manifest {
+    /// The PDF that was split.
+    input: String,
+    files: Vec<File>,
+}
+
+#[derive(Serialize)]
+struct File {
+    /// Path to the file in the output directory.
+    name: String,
+    /// The first and last page it has from the input, starting at one.
+    first_page: usize,
+    last_page: usize,
+    page_count: usize,
+    /// Titles of the bookmarks leading to its heading, starting at the top level.
+    outline: Vec<String>,
+}
+
+impl Manifest {
+    pub fn new(input: &Path, jobs: &[SplitterJob]) -> Self {
+        let files = jobs
+            .iter()
+            .map(|job| File {
+                name: job.filename.to_string_lossy().into_owned(),
+                first_page: job.pages.start + 1,
+                last_page: job.pages.end,
+                page_count: job.pages.len(),
+                outline: job.path.to_owned(),
+            })
+            .collect();
+
+        Self {
+            input: input.to_string_lossy().into_owned(),
+            files,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn save(&self, dir: &Path) -> anyhow::Result<()> {
+        fs::write(dir.join("manifest.json"), self.to_json()).context("Writing manifest")
+    }
+}
@@ -0,0 +1,335 @@
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::Context;
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, StringFormat};
+
+use crate::{
+    args::MergeArgs,
+    input::{self, Input},
+    labels,
+    links::Links,
+    outline::{self, OutlineItem},
+    pdf,
+    source::{Ranges, Source},
+    splitter::{Adjust, SplitterJob},
+    PRODUCER,
+};
+
+/// A PDF to merge, with the pages to take from it.
+#[derive(Clone)]
+pub struct MergeInput {
+    pub path: PathBuf,
+    /// All the pages are used without any ranges.
+    pub ranges: Option<Ranges>,
+}
+
+impl FromStr for MergeInput {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Files with a colon in their name still work as long as they exist
+        if Path::new(s).exists() {
+            return Ok(Self {
+                path: s.into(),
+                ranges: None,
+            });
+        }
+
+        match s.split_once(':') {
+            Some((path, ranges)) => Ok(Self {
+                path: path.into(),
+                ranges: Some(ranges.parse()?),
+            }),
+            None => Ok(Self {
+                path: s.into(),
+                ranges: None,
+            }),
+        }
+    }
+}
+
+/// Combines the inputs into one document with a bookmark for each part, and their own bookmarks under it.
+/// Parts are made the same way as split files, then objects they have in common are merged.
+pub fn merge(args: &MergeArgs) -> anyhow::Result<()> {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let mut pages = Vec::new();
+    let mut items = Vec::new();
+    let mut labels = Vec::new();
+    let mut labeled = false;
+
+    for input in args.inputs.iter() {
+        let name = input.path.to_string_lossy();
+        println!("[*] Loading `{name}`");
+        let old_doc = input::load(&input.path, args.password.as_deref())
+            .with_context(|| format!("Loading `{name}`"))?;
+
+        let parts = match &input.ranges {
+            Some(ranges) => ranges
+                .sections(&old_doc)
+                .with_context(|| format!("Getting pages from `{name}`"))?
+                .into_iter()
+                .map(|x| (x.name, x.start..x.end))
+                .collect(),
+            None => {
+                let stem = input.path.file_stem().unwrap_or_default();
+                let total_pages = old_doc.page_iter().count();
+                vec![(stem.to_string_lossy().into_owned(), 0..total_pages)]
+            }
+        };
+
+        let old_outline = outline::read(&old_doc).unwrap_or_default();
+        let links = Links::new(&old_doc, None);
+        let input = Input::new(old_doc);
+
+        for (name, range) in parts {
+            let job = SplitterJob {
+                filename: PathBuf::new(),
+                pages: range.clone(),
+                outline: Vec::new(),
+                bookmark: None,
+                path: Vec::new(),
+                adjust: Adjust::default(),
+            };
+            let (part, _) =
+                pdf::build(&job, &input, &links).with_context(|| format!("Copying `{name}`"))?;
+
+            let start = pages.len();
+            // Parts without labels are numbered from one, in case another part has labels
+            let part_labels = labels::read(&part);
+            labeled |= !part_labels.is_empty();
+            match part_labels.is_empty() {
+                true => labels.push((start, dictionary! { "S" => "D" })),
+                false => labels.extend(part_labels.into_iter().map(|(i, x)| (start + i, x))),
+            }
+
+            items.push(OutlineItem {
+                title: text(&name),
+                level: 0,
+                page: start,
+                view: vec![Object::Name(b"Fit".to_vec())],
+            });
+            items.extend(
+                outline::section(&old_outline, &range)
+                    .into_iter()
+                    .map(|x| OutlineItem {
+                        level: x.level + 1,
+                        page: start + x.page - range.start,
+                        ..x
+                    }),
+            );
+
+            pages.extend(append(&mut doc, part, pages_id));
+        }
+    }
+
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Count" => pages.len() as u32,
+            "Kids" => pages.iter().map(|&x| x.into()).collect::<Vec<Object>>(),
+        }),
+    );
+
+    let mut catalog = dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    };
+    if let Some(outline) = outline::write(&mut doc, &items, |page| pages[page]) {
+        catalog.set("Outlines", outline);
+        catalog.set("PageMode", "UseOutlines");
+    }
+    if let Some(labels) = labels::tree(labels).filter(|_| labeled) {
+        catalog.set("PageLabels", labels);
+    }
+    let catalog_id = doc.add_object(catalog);
+    let info_id = doc.add_object(dictionary! {
+        "Producer" => Object::String(PRODUCER.to_vec(), StringFormat::Literal),
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.trailer.set("Info", info_id);
+
+    // The catalogs and page trees of the parts aren't needed anymore
+    doc.prune_objects();
+    let merged = dedupe(&mut doc);
+    doc.compress();
+
+    println!(
+        "[*] Saving `{}` ({} pages, {merged} duplicate objects merged)",
+        args.output.to_string_lossy(),
+        pages.len()
+    );
+    doc.save(&args.output).context("Saving merged document")?;
+
+    Ok(())
+}
+
+/// Moves the part's objects into the document, after the ones already in it.
+/// Returns the part's pages, which are moved into the page tree at `pages_id`.
+fn append(doc: &mut Document, part: Document, pages_id: ObjectId) -> Vec<ObjectId> {
+    let offset = doc.max_id;
+    let renumber = |(id, generation): ObjectId| (id + offset, generation);
+
+    let pages = part.page_iter().map(renumber).collect::<Vec<_>>();
+    for (id, mut obj) in part.objects {
+        update_refs(&mut obj, &renumber);
+        doc.objects.insert(renumber(id), obj);
+    }
+    doc.max_id += part.max_id;
+
+    for &page in pages.iter() {
+        if let Ok(Object::Dictionary(page)) = doc.get_object_mut(page) {
+            page.set("Parent", pages_id);
+        }
+    }
+
+    pages
+}
+
+/// Merges objects that are exactly the same, like the fonts in files split from the same document.
+/// Objects can only match once the ones they refer to have been merged, so this goes until nothing changes.
+/// Objects are grouped by a hash of their key, and only compared in full when the hashes match.
+/// Returns how many objects were removed.
+fn dedupe(doc: &mut Document) -> usize {
+    let mut merged = 0;
+    let (mut key, mut other) = (Vec::new(), Vec::new());
+    loop {
+        let mut seen = HashMap::<u64, Vec<ObjectId>>::new();
+        let mut replace = HashMap::new();
+        for (&id, obj) in doc.objects.iter() {
+            if unique(obj) {
+                continue;
+            }
+
+            key.clear();
+            write_key(obj, &mut key);
+            let candidates = seen.entry(hash(&key)).or_default();
+            let first = candidates.iter().copied().find(|first| {
+                other.clear();
+                write_key(&doc.objects[first], &mut other);
+                other == key
+            });
+            match first {
+                Some(first) => {
+                    replace.insert(id, first);
+                }
+                None => candidates.push(id),
+            }
+        }
+
+        if replace.is_empty() {
+            return merged;
+        }
+
+        merged += replace.len();
+        for id in replace.keys() {
+            doc.objects.remove(id);
+        }
+
+        let replace = |id: ObjectId| replace.get(&id).copied().unwrap_or(id);
+        for obj in doc.objects.values_mut() {
+            update_refs(obj, &replace);
+        }
+        for (_, value) in doc.trailer.iter_mut() {
+            update_refs(value, &replace);
+        }
+    }
+}
+
+/// Pages and annotations belong to one place in the document, even if they look the same as another.
+fn unique(obj: &Object) -> bool {
+    let Ok(dict) = obj.as_dict() else {
+        return false;
+    };
+
+    dict.get(b"Type").and_then(Object::as_name).ok() == Some(b"Page")
+        || (dict.has(b"Subtype") && dict.has(b"Rect"))
+}
+
+/// A hash of an object's key, to find the objects it could be the same as.
+fn hash(key: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Writes out everything about the object, so objects with the same key are the same.
+fn write_key(obj: &Object, out: &mut Vec<u8>) {
+    fn bytes(tag: u8, bytes: &[u8], out: &mut Vec<u8>) {
+        out.push(tag);
+        out.extend((bytes.len() as u64).to_le_bytes());
+        out.extend(bytes);
+    }
+
+    fn dict(dict: &Dictionary, out: &mut Vec<u8>) {
+        out.push(b'<');
+        out.extend((dict.len() as u64).to_le_bytes());
+        for (key, value) in dict.iter() {
+            bytes(b'/', key, out);
+            write_key(value, out);
+        }
+    }
+
+    match obj {
+        Object::Null => out.push(b'n'),
+        Object::Boolean(x) => out.extend([b'b', *x as u8]),
+        Object::Integer(x) => {
+            out.push(b'i');
+            out.extend(x.to_le_bytes());
+        }
+        Object::Real(x) => {
+            out.push(b'r');
+            out.extend(x.to_le_bytes());
+        }
+        Object::Name(x) => bytes(b'/', x, out),
+        Object::String(x, _) => bytes(b'(', x, out),
+        Object::Array(array) => {
+            out.push(b'[');
+            out.extend((array.len() as u64).to_le_bytes());
+            for x in array {
+                write_key(x, out);
+            }
+        }
+        Object::Dictionary(x) => dict(x, out),
+        Object::Stream(stream) => {
+            out.push(b's');
+            dict(&stream.dict, out);
+            bytes(b'c', &stream.content, out);
+        }
+        Object::Reference((id, generation)) => {
+            out.push(b'R');
+            out.extend(id.to_le_bytes());
+            out.extend(generation.to_le_bytes());
+        }
+    }
+}
+
+fn update_refs(obj: &mut Object, update: &impl Fn(ObjectId) -> ObjectId) {
+    match obj {
+        Object::Reference(id) => *id = update(*id),
+        Object::Array(array) => array.iter_mut().for_each(|x| update_refs(x, update)),
+        Object::Dictionary(dict) => dict.iter_mut().for_each(|(_, x)| update_refs(x, update)),
+        Object::Stream(stream) => stream
+            .dict
+            .iter_mut()
+            .for_each(|(_, x)| update_refs(x, update)),
+        _ => {}
+    }
+}
+
+/// A text string for a bookmark title, in UTF-16 if it isn't plain ASCII.
+fn text(s: &str) -> Object {
+    if s.is_ascii() {
+        return Object::string_literal(s);
+    }
+
+    let mut bytes = vec![0xFE, 0xFF];
+    bytes.extend(s.encode_utf16().flat_map(u16::to_be_bytes));
+    Object::String(bytes, StringFormat::Hexadecimal)
+}
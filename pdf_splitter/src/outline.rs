@@ -0,0 +1,310 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+use anyhow::Context;
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId};
+
+/// A bookmark from the document outline, flattened into document order.
+#[derive(Clone)]
+pub struct OutlineItem {
+    /// The raw title string, kept as is so it can be copied into the split documents.
+    pub title: Object,
+    /// How deep the bookmark is nested, top level bookmarks are zero.
+    pub level: usize,
+    /// Index of the page the bookmark points to.
+    pub page: usize,
+    /// The rest of the destination after the page, like `/XYZ left top zoom`.
+    pub view: Vec<Object>,
+}
+
+impl OutlineItem {
+    /// Decodes the title, which can be in PDFDocEncoding, or UTF-16 or UTF-8 with a byte order mark.
+    pub fn name(&self) -> String {
+        let Ok(bytes) = self.title.as_str() else {
+            return String::new();
+        };
+
+        match bytes {
+            [0xFE, 0xFF, rest @ ..] => {
+                let chars = rest
+                    .chunks_exact(2)
+                    .map(|x| u16::from_be_bytes([x[0], x[1]]))
+                    .collect::<Vec<_>>();
+                String::from_utf16_lossy(&chars)
+            }
+            _ => lopdf::decode_text_string(&self.title).unwrap_or_default(),
+        }
+    }
+}
+
+/// Reads every bookmark in the document that points to a page in it.
+/// Named destinations are resolved, and bookmarks with actions other than `GoTo` are skipped,
+/// but their children are still included.
+pub fn read(doc: &Document) -> anyhow::Result<Vec<OutlineItem>> {
+    let catalog = doc.catalog().context("Getting catalog")?;
+    let outlines = catalog
+        .get(b"Outlines")
+        .and_then(|x| doc.dereference(x))
+        .and_then(|x| x.1.as_dict())
+        .ok()
+        .context("No Outlines")?;
+
+    let reader = Reader {
+        doc,
+        pages: doc.page_iter().enumerate().map(|(i, id)| (id, i)).collect(),
+        named: named_destinations(doc, catalog),
+    };
+
+    let mut items = Vec::new();
+    let mut seen = HashSet::new();
+    reader.siblings(outlines.get(b"First").ok(), 0, &mut seen, &mut items);
+    Ok(items)
+}
+
+struct Reader<'a> {
+    doc: &'a Document,
+    pages: HashMap<ObjectId, usize>,
+    named: HashMap<Vec<u8>, Vec<Object>>,
+}
+
+impl<'a> Reader<'a> {
+    fn siblings(
+        &self,
+        mut node: Option<&'a Object>,
+        level: usize,
+        seen: &mut HashSet<ObjectId>,
+        items: &mut Vec<OutlineItem>,
+    ) {
+        while let Some(next) = node {
+            // Broken outlines can loop back on themselves
+            if let Ok(id) = next.as_reference() {
+                if !seen.insert(id) {
+                    break;
+                }
+            }
+
+            let Ok((_, Object::Dictionary(dict))) = self.doc.dereference(next) else {
+                break;
+            };
+
+            if let Some(item) = self.item(dict, level) {
+                items.push(item);
+            }
+
+            self.siblings(dict.get(b"First").ok(), level + 1, seen, items);
+            node = dict.get(b"Next").ok();
+        }
+    }
+
+    fn item(&self, dict: &Dictionary, level: usize) -> Option<OutlineItem> {
+        let title = self.doc.dereference(dict.get(b"Title").ok()?).ok()?.1;
+        let dest = target(self.doc, dict)?;
+        let dest = destination(self.doc, dest, &self.named)?;
+        let page = self.pages.get(&dest.first()?.as_reference().ok()?)?;
+
+        Some(OutlineItem {
+            title: title.to_owned(),
+            level,
+            page: *page,
+            view: dest[1..].to_vec(),
+        })
+    }
+}
+
+/// The destination of a bookmark or link, either from its `Dest` or a `GoTo` action.
+pub fn target<'a>(doc: &'a Document, dict: &'a Dictionary) -> Option<&'a Object> {
+    if let Ok(dest) = dict.get(b"Dest") {
+        return Some(dest);
+    }
+
+    let action = doc
+        .dereference(dict.get(b"A").ok()?)
+        .ok()?
+        .1
+        .as_dict()
+        .ok()?;
+    if action.get(b"S").and_then(Object::as_name).ok()? != b"GoTo" {
+        return None;
+    }
+    action.get(b"D").ok()
+}
+
+/// Resolves a destination to its array, looking it up by name if needed.
+pub fn destination(
+    doc: &Document,
+    dest: &Object,
+    named: &HashMap<Vec<u8>, Vec<Object>>,
+) -> Option<Vec<Object>> {
+    match doc.dereference(dest).ok()?.1 {
+        Object::Array(array) => Some(array.to_owned()),
+        Object::Name(name) | Object::String(name, _) => named.get(name).cloned(),
+        // Named destinations can also be a dictionary with the array in `D`
+        Object::Dictionary(dict) => destination(doc, dict.get(b"D").ok()?, named),
+        _ => None,
+    }
+}
+
+/// Every named destination from the catalog's `Dests` dictionary and the `Dests` name tree.
+pub fn named_destinations(doc: &Document, catalog: &Dictionary) -> HashMap<Vec<u8>, Vec<Object>> {
+    let mut raw = HashMap::new();
+    if let Ok(dests) = catalog.get(b"Dests").and_then(|x| doc.dereference(x)) {
+        if let Ok(dests) = dests.1.as_dict() {
+            for (key, value) in dests.iter() {
+                raw.insert(key.to_owned(), value.to_owned());
+            }
+        }
+    }
+
+    if let Ok(tree) = doc
+        .get_dict_in_dict(catalog, b"Names")
+        .and_then(|x| doc.get_dict_in_dict(x, b"Dests"))
+    {
+        name_tree(doc, tree, &mut raw, &mut HashSet::new());
+    }
+
+    let empty = HashMap::new();
+    raw.iter()
+        .filter_map(|(key, value)| Some((key.to_owned(), destination(doc, value, &empty)?)))
+        .collect()
+}
+
+fn name_tree(
+    doc: &Document,
+    node: &Dictionary,
+    out: &mut HashMap<Vec<u8>, Object>,
+    seen: &mut HashSet<ObjectId>,
+) {
+    if let Ok(names) = node.get(b"Names").and_then(Object::as_array) {
+        for pair in names.chunks_exact(2) {
+            if let Ok(key) = pair[0].as_str() {
+                out.insert(key.to_owned(), pair[1].to_owned());
+            }
+        }
+    }
+
+    let Ok(kids) = node.get(b"Kids").and_then(Object::as_array) else {
+        return;
+    };
+    for kid in kids.iter().filter_map(|x| x.as_reference().ok()) {
+        if seen.insert(kid) {
+            if let Ok(kid) = doc.get_dictionary(kid) {
+                name_tree(doc, kid, out, seen);
+            }
+        }
+    }
+}
+
+/// The bookmarks pointing into `pages`, with their levels made relative to the shallowest one
+/// so the section's own bookmark ends up at the top.
+pub fn section(items: &[OutlineItem], pages: &Range<usize>) -> Vec<OutlineItem> {
+    let items = items
+        .iter()
+        .filter(|x| pages.contains(&x.page))
+        .collect::<Vec<_>>();
+    let root = items.iter().map(|x| x.level).min().unwrap_or(0);
+
+    items
+        .into_iter()
+        .map(|x| OutlineItem {
+            level: x.level - root,
+            ..x.to_owned()
+        })
+        .collect()
+}
+
+/// Adds the bookmarks to a new document as its outline.
+/// `page` maps the page index in the old document to the page in the new one.
+/// Returns the ID of the outline dictionary, or `None` if there are no bookmarks.
+pub fn write(
+    doc: &mut Document,
+    items: &[OutlineItem],
+    page: impl Fn(usize) -> ObjectId,
+) -> Option<ObjectId> {
+    if items.is_empty() {
+        return None;
+    }
+
+    // Each bookmark's parent is the last one before it with a lower level
+    let mut roots = Vec::new();
+    let mut children = vec![Vec::new(); items.len()];
+    let mut stack = Vec::<usize>::new();
+    for (i, item) in items.iter().enumerate() {
+        while stack.last().is_some_and(|&x| items[x].level >= item.level) {
+            stack.pop();
+        }
+
+        match stack.last() {
+            Some(&parent) => children[parent].push(i),
+            None => roots.push(i),
+        }
+        stack.push(i);
+    }
+
+    let root_id = doc.new_object_id();
+    let writer = Writer {
+        items,
+        children,
+        ids: items.iter().map(|_| doc.new_object_id()).collect(),
+        page,
+    };
+    let count = writer.write(doc, root_id, &roots);
+
+    doc.objects.insert(
+        root_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Outlines",
+            "First" => writer.ids[roots[0]],
+            "Last" => writer.ids[*roots.last().unwrap()],
+            "Count" => count as i64,
+        }),
+    );
+    Some(root_id)
+}
+
+struct Writer<'a, F> {
+    items: &'a [OutlineItem],
+    children: Vec<Vec<usize>>,
+    ids: Vec<ObjectId>,
+    page: F,
+}
+
+impl<F: Fn(usize) -> ObjectId> Writer<'_, F> {
+    /// Writes a list of siblings and everything under them.
+    /// Returns the total number of bookmarks written, which is the `Count` of their parent.
+    fn write(&self, doc: &mut Document, parent: ObjectId, siblings: &[usize]) -> usize {
+        let mut count = 0;
+        for (n, &i) in siblings.iter().enumerate() {
+            let item = &self.items[i];
+            let mut dest = vec![Object::Reference((self.page)(item.page))];
+            dest.extend(item.view.iter().cloned());
+
+            let mut dict = dictionary! {
+                "Title" => item.title.clone(),
+                "Parent" => parent,
+                "Dest" => dest,
+            };
+            if n > 0 {
+                dict.set("Prev", self.ids[siblings[n - 1]]);
+            }
+            if let Some(&next) = siblings.get(n + 1) {
+                dict.set("Next", self.ids[next]);
+            }
+
+            let kids = &self.children[i];
+            if !kids.is_empty() {
+                let descendants = self.write(doc, self.ids[i], kids);
+                dict.set("First", self.ids[kids[0]]);
+                dict.set("Last", self.ids[*kids.last().unwrap()]);
+                dict.set("Count", descendants as i64);
+                count += descendants;
+            }
+
+            doc.objects.insert(self.ids[i], Object::Dictionary(dict));
+            count += 1;
+        }
+
+        count
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::{HashMap, HashSet};
+
+use lopdf::{content::Content, Dictionary, Document, Object};
+
+/// The kinds of resources that are only used by name from the content stream.
+const PRUNABLE: [&[u8]; 5] = [b"Font", b"XObject", b"ExtGState", b"Shading", b"Pattern"];
+
+/// Removes the fonts, images and such from a page's resources that its content never uses.
+/// This matters most when the resources are inherited from the page tree, where they can be shared by every page.
+/// Returns the new resources and how many were removed,
+/// or `None` if the content can't be read or it isn't safe to tell what's used.
+pub fn prune(doc: &Document, resources: &Object, content: &[u8]) -> Option<(Dictionary, usize)> {
+    let resources = doc.dereference(resources).ok()?.1.as_dict().ok()?;
+    let content = Content::decode(content).ok()?;
+
+    let mut used = HashMap::<&[u8], HashSet<&[u8]>>::new();
+    for operation in content.operations.iter() {
+        let (kind, name): (&[u8], _) =
+            match (operation.operator.as_str(), operation.operands.as_slice()) {
+                ("Tf", [name, ..]) => (b"Font", name),
+                ("Do", [name]) => (b"XObject", name),
+                ("gs", [name]) => (b"ExtGState", name),
+                ("sh", [name]) => (b"Shading", name),
+                ("scn" | "SCN", [.., name]) => (b"Pattern", name),
+                _ => continue,
+            };
+
+        if let Ok(name) = name.as_name() {
+            used.entry(kind).or_default().insert(name);
+        }
+    }
+
+    let mut pruned = resources.to_owned();
+    let mut removed = 0;
+    for kind in PRUNABLE {
+        let Ok(Object::Dictionary(all)) =
+            resources.get(kind).and_then(|x| Ok(doc.dereference(x)?.1))
+        else {
+            continue;
+        };
+
+        let used = used.remove(kind).unwrap_or_default();
+        // Old form XObjects without their own resources use the page's
+        if kind == b"XObject"
+            && all
+                .iter()
+                .any(|(name, x)| used.contains(name.as_slice()) && !has_resources(doc, x))
+        {
+            return None;
+        }
+
+        let kept = all
+            .iter()
+            .filter(|(name, _)| used.contains(name.as_slice()))
+            .map(|(name, x)| (name.to_owned(), x.to_owned()))
+            .collect::<Dictionary>();
+        removed += all.len() - kept.len();
+        pruned.set(kind, kept);
+    }
+
+    Some((pruned, removed))
+}
+
+fn has_resources(doc: &Document, xobject: &Object) -> bool {
+    let Ok((_, Object::Stream(stream))) = doc.dereference(xobject) else {
+        return false;
+    };
+
+    stream.dict.get(b"Subtype").and_then(Object::as_name).ok() != Some(b"Form")
+        || stream.dict.has(b"Resources")
+}
@@ -0,0 +1,328 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+    str::FromStr,
+};
+
+use anyhow::{bail, Context};
+use lopdf::{content::Content, Dictionary, Document, Encoding, Object, ObjectId};
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use regex::Regex;
+
+use crate::{
+    outline,
+    pdf::inherited,
+    splitter::{self, Section, Special, Splitter, SplitterJob},
+};
+
+/// Somewhere to get sections from other than the outline, for documents that don't have one.
+pub trait Source {
+    /// The sections to split the document into, in order.
+    fn sections(&self, doc: &Document) -> anyhow::Result<Vec<Section>>;
+}
+
+/// Makes jobs out of the sections from a source.
+/// Sections that don't match `should_split` are skipped.
+pub fn jobs(
+    doc: &Document,
+    source: &dyn Source,
+    splitter: &dyn Splitter,
+) -> anyhow::Result<Vec<SplitterJob>> {
+    // Keep any bookmarks there are, even though they aren't used for splitting
+    let outline = outline::read(doc).unwrap_or_default();
+
+    let jobs = source
+        .sections(doc)?
+        .into_iter()
+        .filter(|x| !matches!(x.special, Special::None) || splitter.should_split(x))
+        .map(|x| x.as_job(splitter))
+        .collect::<Vec<_>>();
+
+    Ok(splitter::finish(jobs, &outline, doc.page_iter().count()))
+}
+
+/// Named page ranges, like `1-4:Intro,5-20:Chapter 1`.
+/// Pages start at one and ranges include their last page.
+/// Pages not in any range are left out.
+#[derive(Clone)]
+pub struct Ranges(Vec<(Range<usize>, String)>);
+
+impl FromStr for Ranges {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ranges = Vec::new();
+        for part in s.split(',') {
+            let (pages, name) = part.split_once(':').unwrap_or((part, ""));
+            let (start, end) = pages.split_once('-').unwrap_or((pages, pages));
+            let (Ok(start), Ok(end)) = (start.trim().parse(), end.trim().parse()) else {
+                return Err(format!("invalid page range `{pages}`"));
+            };
+
+            if start == 0 || end < start {
+                return Err(format!("invalid page range `{pages}`"));
+            }
+
+            let name = match name.trim() {
+                "" if start == end => format!("Page {start}"),
+                "" => format!("Pages {start}-{end}"),
+                name => name.to_owned(),
+            };
+            ranges.push((start - 1..end, name));
+        }
+
+        Ok(Self(ranges))
+    }
+}
+
+impl Source for Ranges {
+    fn sections(&self, doc: &Document) -> anyhow::Result<Vec<Section>> {
+        let total_pages = doc.page_iter().count();
+        let mut sections = Vec::new();
+        for (pages, name) in self.0.iter() {
+            if pages.end > total_pages {
+                bail!(
+                    "Page range {}-{} is past the end of the document ({total_pages} pages)",
+                    pages.start + 1,
+                    pages.end
+                );
+            }
+
+            sections.push(Section {
+                name: name.to_owned(),
+                start: pages.start,
+                end: pages.end,
+                ..Default::default()
+            });
+        }
+
+        Ok(sections)
+    }
+}
+
+/// Parts with the same number of pages, except for the last one.
+pub struct Chunks(pub usize);
+
+impl Source for Chunks {
+    fn sections(&self, doc: &Document) -> anyhow::Result<Vec<Section>> {
+        let total_pages = doc.page_iter().count();
+        Ok(parts((0..total_pages).step_by(self.0.max(1)), total_pages))
+    }
+}
+
+/// Parts that come out to about the given number of bytes.
+/// The size of each page is estimated from its content and the resources it uses,
+/// with resources shared between pages only counted once per part.
+pub struct Size(pub u64);
+
+impl Source for Size {
+    fn sections(&self, doc: &Document) -> anyhow::Result<Vec<Section>> {
+        let total_pages = doc.page_iter().count();
+        let mut starts = vec![0];
+        let mut seen = HashSet::new();
+        let mut size = 0;
+
+        for (i, page) in doc.page_iter().enumerate() {
+            let mut bytes = page_size(doc, page, &mut seen);
+
+            // A page bigger than the target still gets a part to itself
+            if size + bytes > self.0 && size > 0 {
+                starts.push(i);
+                seen.clear();
+                bytes = page_size(doc, page, &mut seen);
+                size = 0;
+            }
+            size += bytes;
+        }
+
+        Ok(parts(starts.into_iter(), total_pages))
+    }
+}
+
+/// Parses sizes like `500K` or `1.5MB` into bytes.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim().to_ascii_uppercase();
+    let number = s.trim_end_matches(|x: char| x.is_ascii_alphabetic());
+    let scale = match &s[number.len()..] {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        unit => return Err(format!("unknown unit `{unit}`")),
+    };
+
+    match number.trim().parse::<f64>() {
+        Ok(number) if number > 0.0 => Ok((number * scale as f64) as u64),
+        _ => Err(format!("invalid size `{s}`")),
+    }
+}
+
+/// Starts a new section on every page with text that matches the regex, named after the match and numbered if it repeats.
+/// Any pages before the first match go into a start section.
+pub struct PageText(pub Regex);
+
+impl Source for PageText {
+    fn sections(&self, doc: &Document) -> anyhow::Result<Vec<Section>> {
+        let pages = doc.page_iter().collect::<Vec<_>>();
+        let total_pages = pages.len();
+        let matches = pages
+            .into_par_iter()
+            .map(|page| {
+                let text = page_text(doc, page).unwrap_or_default();
+                self.0.find(&text).map(|x| x.as_str().trim().to_owned())
+            })
+            .collect::<Vec<_>>();
+
+        // Headings that repeat, like a "Chapter 1" in every part, are numbered so they don't overwrite each other
+        let mut seen = HashMap::<String, usize>::new();
+        let mut sections = Vec::<Section>::new();
+        for (page, name) in matches.into_iter().enumerate() {
+            let Some(mut name) = name else {
+                continue;
+            };
+            let count = seen.entry(name.clone()).or_default();
+            *count += 1;
+            if *count > 1 {
+                name = format!("{name} ({count})");
+            }
+
+            match sections.last_mut() {
+                Some(last) => last.end = page,
+                None if page != 0 => sections.push(Section {
+                    special: Special::StartSlack,
+                    end: page,
+                    ..Default::default()
+                }),
+                None => {}
+            }
+
+            sections.push(Section {
+                name,
+                start: page,
+                end: total_pages,
+                ..Default::default()
+            });
+        }
+
+        (!sections.is_empty())
+            .then_some(sections)
+            .context("No pages matched")
+    }
+}
+
+/// The text on a page, like [`Document::extract_text`] but from the page's ID.
+/// That looks up every page again for each call, which adds up when it's called once per page.
+fn page_text(doc: &Document, page: ObjectId) -> lopdf::Result<String> {
+    fn dict<'a>(doc: &'a Document, obj: &'a Object) -> Option<&'a Dictionary> {
+        doc.dereference(obj).and_then(|x| x.1.as_dict()).ok()
+    }
+
+    let fonts = inherited(doc, doc.get_dictionary(page)?, b"Resources")
+        .and_then(|x| dict(doc, x))
+        .and_then(|x| x.get(b"Font").ok())
+        .and_then(|x| dict(doc, x));
+    let encodings = fonts
+        .into_iter()
+        .flat_map(Dictionary::iter)
+        .filter_map(|(name, font)| {
+            Some((
+                name.as_slice(),
+                dict(doc, font)?.get_font_encoding(doc).ok(),
+            ))
+        })
+        .collect::<HashMap<_, _>>();
+    let content = Content::decode(&doc.get_page_content(page)?)?;
+
+    let mut text = String::new();
+    let mut font = None;
+    for operation in &content.operations {
+        match operation.operator.as_str() {
+            "Tf" => {
+                let name = operation.operands.first().and_then(|x| x.as_name().ok());
+                font = name.and_then(|x| encodings.get(x));
+            }
+            "Tj" | "TJ" => {
+                if let Some(encoding) = font {
+                    collect_text(&mut text, encoding.as_ref(), &operation.operands);
+                }
+            }
+            "ET" if !text.ends_with('\n') => text.push('\n'),
+            _ => {}
+        }
+    }
+    Ok(text)
+}
+
+/// Adds the strings shown by a text operator, with a space for each array or wide gap between them.
+/// Fonts without an encoding lopdf knows, like the standard fonts that leave it out, are read one byte per character.
+fn collect_text(text: &mut String, encoding: Option<&Encoding>, operands: &[Object]) {
+    for operand in operands {
+        match operand {
+            Object::String(bytes, _) => match encoding {
+                Some(encoding) => {
+                    text.push_str(&Document::decode_text(encoding, bytes).unwrap_or_default())
+                }
+                None => text.extend(bytes.iter().map(|&x| x as char)),
+            },
+            Object::Array(array) => {
+                collect_text(text, encoding, array);
+                text.push(' ');
+            }
+            Object::Integer(x) if *x < -100 => text.push(' '),
+            _ => {}
+        }
+    }
+}
+
+/// Numbered parts starting at each of the pages.
+fn parts(starts: impl Iterator<Item = usize>, total_pages: usize) -> Vec<Section> {
+    let starts = starts.collect::<Vec<_>>();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| Section {
+            name: format!("Part {}", i + 1),
+            start,
+            end: starts.get(i + 1).copied().unwrap_or(total_pages),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Roughly how many bytes a page adds to a document that already has the objects in `seen`.
+fn page_size(doc: &Document, page: ObjectId, seen: &mut HashSet<ObjectId>) -> u64 {
+    let Ok(page) = doc.get_dictionary(page) else {
+        return 0;
+    };
+
+    [b"Contents".as_slice(), b"Resources"]
+        .into_iter()
+        .filter_map(|x| page.get(x).ok())
+        .map(|x| object_size(doc, x, seen))
+        .sum()
+}
+
+fn object_size(doc: &Document, obj: &Object, seen: &mut HashSet<ObjectId>) -> u64 {
+    match obj {
+        Object::Reference(id) => match seen.insert(*id) {
+            true => doc.get_object(*id).map_or(0, |x| object_size(doc, x, seen)),
+            false => 0,
+        },
+        Object::Array(array) => array.iter().map(|x| object_size(doc, x, seen)).sum(),
+        Object::Dictionary(dict) => dict
+            .iter()
+            .map(|(key, value)| key.len() as u64 + object_size(doc, value, seen))
+            .sum(),
+        Object::Stream(stream) => {
+            let dict = stream
+                .dict
+                .iter()
+                .map(|(key, value)| key.len() as u64 + object_size(doc, value, seen))
+                .sum::<u64>();
+            dict + stream.content.len() as u64
+        }
+        Object::String(string, _) => string.len() as u64,
+        Object::Name(name) => name.len() as u64,
+        _ => 8,
+    }
+}
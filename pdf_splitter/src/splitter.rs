@@ -1,11 +1,38 @@
-use std::{borrow::Cow, collections::BTreeMap, ops::Range, path::PathBuf, sync::Arc};
+use std::{
+    borrow::Cow,
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-use anyhow::Context;
-use lopdf::{Destination, Document, Outline};
+use lopdf::Document;
+
+use crate::outline::{self, OutlineItem};
 
 pub trait Splitter {
     fn name<'a>(&self, section: &'a Section) -> Cow<'a, str>;
     fn should_split(&self, section: &Section) -> bool;
+
+    /// Changes to make to the section's pages once all the sections are found.
+    fn adjust(&self, _section: &Section) -> Adjust {
+        Adjust::default()
+    }
+
+    /// Sub directory to put the section's file in, under the directory it would go in otherwise.
+    fn dir(&self, _section: &Section) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// Changes to a section's pages.
+#[derive(Clone, Copy, Default)]
+pub struct Adjust {
+    /// Pages to include before the section.
+    pub before: usize,
+    /// Pages to include after the section.
+    pub after: usize,
+    /// Sections with fewer pages than this are merged into the one before them.
+    pub merge_shorter_than: usize,
 }
 
 #[derive(Default)]
@@ -15,6 +42,8 @@ pub struct Section {
     pub name: String,
     pub start: usize,
     pub end: usize,
+    /// Index of the bookmark the section comes from, if it's from the outline.
+    pub bookmark: Option<usize>,
 }
 
 #[derive(Default)]
@@ -25,47 +54,90 @@ pub enum Special {
     None,
 }
 
+/// How deep into the outline to split.
+#[derive(Clone, Copy)]
+pub enum Depth {
+    All,
+    Level(usize),
+}
+
+impl FromStr for Depth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "-1" | "all" => Ok(Depth::All),
+            _ => s
+                .parse()
+                .map(Depth::Level)
+                .map_err(|_| "expected a depth, or -1 for all headings".to_owned()),
+        }
+    }
+}
+
+impl Depth {
+    fn includes(self, level: usize) -> bool {
+        match self {
+            Depth::All => true,
+            Depth::Level(depth) => level <= depth,
+        }
+    }
+}
+
 pub struct SplitterJob {
     pub filename: PathBuf,
     pub pages: Range<usize>,
+    /// The bookmarks that point into this job's pages.
+    pub outline: Vec<OutlineItem>,
+    /// The bookmark the job's section comes from.
+    pub bookmark: Option<usize>,
+    /// Titles of the bookmarks leading to this job's heading, starting at the top level.
+    pub path: Vec<String>,
+    pub adjust: Adjust,
 }
 
 impl Section {
-    fn as_job(&self, splitter: &dyn Splitter) -> SplitterJob {
-        let filename = PathBuf::from(format!("{}.pdf", splitter.name(self)));
-        let pages = self.start..self.end;
+    pub fn as_job(&self, splitter: &dyn Splitter) -> SplitterJob {
+        self.as_job_in(Path::new(""), "", splitter)
+    }
 
-        SplitterJob { filename, pages }
+    fn as_job_in(&self, dir: &Path, prefix: &str, splitter: &dyn Splitter) -> SplitterJob {
+        let dir = in_dir(dir, self, splitter);
+        self.as_file(
+            dir.join(format!("{prefix}{}.pdf", splitter.name(self))),
+            splitter,
+        )
+    }
+
+    fn as_file(&self, filename: PathBuf, splitter: &dyn Splitter) -> SplitterJob {
+        SplitterJob {
+            filename,
+            pages: self.start..self.end,
+            outline: Vec::new(),
+            bookmark: self.bookmark,
+            path: Vec::new(),
+            adjust: splitter.adjust(self),
+        }
     }
 }
 
 pub fn jobs(
-    doc: Arc<Document>,
+    doc: &Document,
     splitter: &dyn Splitter,
-    depth: usize,
+    depth: Depth,
 ) -> anyhow::Result<Vec<SplitterJob>> {
     let total_pages = doc.page_iter().count();
     let mut jobs: Vec<SplitterJob> = Vec::new();
 
-    let mut destinations = BTreeMap::new();
-    let bookmarks = doc
-        .get_outlines(None, None, &mut destinations)
-        .context("Getting outlines")?
-        .context("No Outlines")?;
-
-    let mut outlines = Vec::new();
-    for i in bookmarks {
-        get_outlines(&mut outlines, &i, 0);
-    }
+    let outline = outline::read(doc)?;
 
     // TODO: Split depth into splitter?
-    for i in outlines.iter().filter(|x| x.1 == depth) {
-        let title = i.0.title().unwrap().as_string().unwrap();
-        let reference = i.0.page().unwrap().as_reference().unwrap();
-        let page = doc
-            .page_iter()
-            .position(|x| x == reference)
-            .context("Page not found")?;
+    let at_depth = |level| match depth {
+        Depth::All => true,
+        Depth::Level(depth) => level == depth,
+    };
+    for (bookmark, i) in outline.iter().enumerate().filter(|x| at_depth(x.1.level)) {
+        let page = i.page;
 
         if let Some(i) = jobs.last_mut() {
             if i.pages.end == 0 {
@@ -75,17 +147,18 @@ pub fn jobs(
 
         let section = Section {
             special: Special::None,
-            level: i.1,
-            name: title.into_owned(),
+            level: i.level,
+            name: i.name(),
             start: page,
             end: 0,
+            bookmark: Some(bookmark),
         };
 
         if splitter.should_split(&section) {
             if section.start != 0 && jobs.is_empty() {
                 let section = Section {
                     special: Special::StartSlack,
-                    level: i.1,
+                    level: i.level,
                     end: section.start,
                     ..Default::default()
                 };
@@ -96,6 +169,13 @@ pub fn jobs(
         }
     }
 
+    // The last section runs to the end of the document
+    if let Some(i) = jobs.last_mut() {
+        if i.pages.end == 0 {
+            i.pages.end = total_pages;
+        }
+    }
+
     if let Some(i) = jobs.last() {
         if i.pages.end != total_pages {
             let section = Section {
@@ -109,18 +189,220 @@ pub fn jobs(
         }
     }
 
-    Ok(jobs)
+    Ok(finish(jobs, &outline, total_pages))
 }
 
-fn get_outlines(outlines: &mut Vec<(Destination, usize)>, outline: &Outline, depth: usize) {
-    match outline {
-        Outline::Destination(dest) => {
-            outlines.push((dest.clone(), depth));
+/// The directory for a section, with the splitter's sub directory for it if it has one.
+fn in_dir(dir: &Path, section: &Section, splitter: &dyn Splitter) -> PathBuf {
+    match splitter.dir(section) {
+        Some(sub) => dir.join(sub),
+        None => dir.to_owned(),
+    }
+}
+
+/// Applies each job's adjustments, then gives it the bookmarks in its pages and its outline path.
+pub fn finish(
+    jobs: Vec<SplitterJob>,
+    outline: &[OutlineItem],
+    total_pages: usize,
+) -> Vec<SplitterJob> {
+    let mut out = Vec::<SplitterJob>::with_capacity(jobs.len());
+    for mut job in jobs {
+        // Sections that aren't from the outline get the path of the first heading in them
+        let bookmark = job.bookmark.or_else(|| shallowest(outline, &job.pages));
+        job.path = bookmark
+            .map(|x| outline_path(outline, x))
+            .unwrap_or_default();
+
+        // Short sections are merged into the one right before them, as long as it's in the same directory
+        let short = job.pages.len() < job.adjust.merge_shorter_than;
+        if let Some(last) = out.last_mut().filter(|x| {
+            short && x.pages.end == job.pages.start && x.filename.parent() == job.filename.parent()
+        }) {
+            last.pages.end = job.pages.end;
+            continue;
         }
-        Outline::SubOutlines(dest) => {
-            for i in dest {
-                get_outlines(outlines, i, depth + 1);
+
+        out.push(job);
+    }
+
+    for job in out.iter_mut() {
+        let Adjust { before, after, .. } = job.adjust;
+        job.pages =
+            job.pages.start.saturating_sub(before)..(job.pages.end + after).min(total_pages);
+        job.outline = outline::section(outline, &job.pages);
+    }
+
+    out
+}
+
+/// The first of the shallowest bookmarks pointing into the pages.
+fn shallowest(outline: &[OutlineItem], pages: &Range<usize>) -> Option<usize> {
+    outline
+        .iter()
+        .enumerate()
+        .filter(|(_, x)| pages.contains(&x.page))
+        .min_by_key(|(_, x)| x.level)
+        .map(|(i, _)| i)
+}
+
+/// Titles of the bookmark and its parents, starting with the top level.
+fn outline_path(outline: &[OutlineItem], i: usize) -> Vec<String> {
+    let item = &outline[i];
+    let mut path = vec![item.name()];
+    let mut level = item.level;
+    for parent in outline[..i].iter().rev() {
+        if parent.level < level {
+            path.push(parent.name());
+            level = parent.level;
+        }
+    }
+
+    path.reverse();
+    path
+}
+
+/// Makes a section name safe to use as a file name, by replacing spaces and removing colons and slashes.
+pub fn clean_name(name: &str) -> String {
+    name.replace(' ', "_").replace([':', '/'], "")
+}
+
+/// Splits the document following the outline, with a directory for each heading that has sub-sections
+/// and a PDF for each one that doesn't.
+/// Headings deeper than `depth` stay in their parent's PDF, and only the top level headings are checked with `should_split`.
+/// Everything is prefixed with its position so the files sort in reading order.
+pub fn nested_jobs(
+    doc: &Document,
+    splitter: &dyn Splitter,
+    depth: Depth,
+) -> anyhow::Result<Vec<SplitterJob>> {
+    let total_pages = doc.page_iter().count();
+    let outline = outline::read(doc)?;
+
+    let sections = outline
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            // Sections run until the next heading that isn't under them,
+            // and get at least the page they start on if it's shared with the next one.
+            let end = outline[i + 1..]
+                .iter()
+                .find(|x| x.level <= item.level)
+                .map_or(total_pages, |x| x.page);
+            Section {
+                special: Special::None,
+                level: item.level,
+                name: item.name(),
+                start: item.page,
+                end: end.max(item.page + 1),
+                bookmark: Some(i),
             }
+        })
+        .collect::<Vec<_>>();
+
+    let chapters = children(&sections)
+        .into_iter()
+        .filter(|x| splitter.should_split(x.0))
+        .collect::<Vec<_>>();
+    let (Some(first), Some(last)) = (chapters.first(), chapters.last()) else {
+        return Ok(Vec::new());
+    };
+
+    let mut jobs = Vec::new();
+    let root = Path::new("");
+    // The slack after the last chapter is numbered after it, so everything at the top level shares one width
+    let end_slack = last.0.end < total_pages;
+    let last_number = chapters.len() + usize::from(end_slack);
+    if first.0.start != 0 {
+        let section = Section {
+            special: Special::StartSlack,
+            end: first.0.start,
+            ..Default::default()
+        };
+        jobs.push(section.as_job_in(root, &prefix(0, last_number), splitter));
+    }
+
+    if end_slack {
+        let section = Section {
+            special: Special::EndSlack,
+            start: last.0.end,
+            end: total_pages,
+            ..Default::default()
+        };
+        jobs.push(section.as_job_in(root, &prefix(last_number, last_number), splitter));
+    }
+
+    nested(&chapters, root, last_number, splitter, depth, &mut jobs);
+
+    jobs.sort_by(|a, b| a.filename.cmp(&b.filename));
+    Ok(finish(jobs, &outline, total_pages))
+}
+
+/// Adds the jobs for a list of sibling sections, numbered from one and padded to fit `last_number`.
+fn nested(
+    siblings: &[(&Section, &[Section])],
+    dir: &Path,
+    last_number: usize,
+    splitter: &dyn Splitter,
+    depth: Depth,
+    jobs: &mut Vec<SplitterJob>,
+) {
+    for (i, (section, under)) in siblings.iter().enumerate() {
+        let prefix = prefix(i + 1, last_number);
+        let kids = children(under);
+        let split = kids.first().filter(|x| depth.includes(x.0.level));
+
+        let Some(&(first, _)) = split else {
+            jobs.push(section.as_job_in(dir, &prefix, splitter));
+            continue;
+        };
+
+        let dir =
+            in_dir(dir, section, splitter).join(format!("{prefix}{}", splitter.name(section)));
+        // Any pages before the first sub-section go at the start of the directory
+        if first.start > section.start {
+            let intro = Section {
+                special: Special::None,
+                level: section.level,
+                name: section.name.to_owned(),
+                start: section.start,
+                end: first.start,
+                bookmark: section.bookmark,
+            };
+            // The intro is already in the section's directory, so it doesn't go in a sub directory again
+            let prefix = self::prefix(0, kids.len());
+            let filename = dir.join(format!("{prefix}{}.pdf", splitter.name(&intro)));
+            jobs.push(intro.as_file(filename, splitter));
         }
+
+        nested(&kids, &dir, kids.len(), splitter, depth, jobs);
     }
 }
+
+/// Groups a flattened part of the outline into its top level sections, each with the sections under it.
+fn children(sections: &[Section]) -> Vec<(&Section, &[Section])> {
+    let Some(top) = sections.iter().map(|x| x.level).min() else {
+        return Vec::new();
+    };
+
+    let starts = sections
+        .iter()
+        .enumerate()
+        .filter(|x| x.1.level == top)
+        .map(|x| x.0)
+        .collect::<Vec<_>>();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let end = starts.get(i + 1).copied().unwrap_or(sections.len());
+            (&sections[start], &sections[start + 1..end])
+        })
+        .collect()
+}
+
+/// A zero padded position, wide enough for every number up to `last` so they sort in order.
+fn prefix(i: usize, last: usize) -> String {
+    let width = last.to_string().len().max(2);
+    format!("{i:0width$}_")
+}
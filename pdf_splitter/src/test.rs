@@ -0,0 +1,820 @@
+use std::{collections::BTreeMap, fs, ops::Range, path::PathBuf, sync::Arc};
+
+use clap::Parser;
+use lopdf::{
+    content::{Content, Operation},
+    dictionary,
+    encryption::crypt_filters::{Aes128CryptFilter, Aes256CryptFilter, CryptFilter},
+    Dictionary, Document, EncryptionState, EncryptionVersion, Object, ObjectId, Permissions,
+    Stream, StringFormat,
+};
+use regex::Regex;
+
+use crate::{
+    args::{Args, MergeArgs},
+    config::{self, ConfigSplitter},
+    input::{self, Input},
+    links::Links,
+    merge, outline, pdf,
+    source::{self, Chunks, PageText, Ranges, Source},
+    splitter::{self, Adjust, Depth, Section, Splitter, SplitterJob},
+};
+
+/// Makes a document with one page of text per name.
+/// Every page gets its resources (with a font that's never used), size and rotation from the page tree,
+/// with the first page on its own and the rest under a `Pages` node that rotates them.
+fn document(names: &[&str], metadata: bool) -> Document {
+    let mut doc = Document::with_version("1.5");
+    let root_id = doc.new_object_id();
+    let rotated_id = doc.new_object_id();
+
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Courier",
+    });
+    let unused_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+    });
+
+    let mut pages = Vec::new();
+    for (i, name) in names.iter().enumerate() {
+        let content = Content {
+            operations: vec![
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec!["F1".into(), 24.into()]),
+                Operation::new("Td", vec![50.into(), 300.into()]),
+                Operation::new("Tj", vec![Object::string_literal(*name)]),
+                Operation::new("ET", vec![]),
+            ],
+        };
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+        let parent = if i == 0 { root_id } else { rotated_id };
+        pages.push(doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => parent,
+            "Contents" => content_id,
+        }));
+    }
+
+    let kids = |pages: &[ObjectId]| pages.iter().map(|&x| x.into()).collect::<Vec<Object>>();
+    doc.objects.insert(
+        rotated_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Parent" => root_id,
+            "Kids" => kids(&pages[1..]),
+            "Count" => pages.len() as i64 - 1,
+            "Rotate" => 90,
+        }),
+    );
+
+    let mut first = kids(&pages[..1]);
+    first.push(rotated_id.into());
+    doc.objects.insert(
+        root_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => first,
+            "Count" => pages.len() as i64,
+            "Resources" => dictionary! {
+                "Font" => dictionary! { "F1" => font_id, "F2" => unused_id },
+            },
+            "MediaBox" => vec![0.into(), 0.into(), 300.into(), 400.into()],
+            "CropBox" => vec![10.into(), 10.into(), 290.into(), 390.into()],
+        }),
+    );
+
+    let mut catalog = dictionary! {
+        "Type" => "Catalog",
+        "Pages" => root_id,
+    };
+    if metadata {
+        let metadata = doc.add_object(Stream::new(
+            dictionary! { "Type" => "Metadata", "Subtype" => "XML" },
+            b"<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"/>".to_vec(),
+        ));
+        catalog.set("Metadata", metadata);
+    }
+
+    let catalog_id = doc.add_object(catalog);
+    doc.trailer.set("Root", catalog_id);
+    doc
+}
+
+/// Gives the document an outline with a bookmark for each title, level and page.
+fn add_outline(doc: &mut Document, items: &[(&str, usize, usize)]) {
+    let pages = doc.page_iter().collect::<Vec<_>>();
+    let items = items
+        .iter()
+        .map(|&(title, level, page)| outline::OutlineItem {
+            title: Object::string_literal(title),
+            level,
+            page,
+            view: vec!["Fit".into()],
+        })
+        .collect::<Vec<_>>();
+
+    let outlines = outline::write(doc, &items, |i| pages[i]).unwrap();
+    doc.catalog_mut().unwrap().set("Outlines", outlines);
+}
+
+/// Splits out the pages and loads the document that was written.
+fn split(doc: Document, pages: Range<usize>, name: &str) -> Document {
+    let job = SplitterJob {
+        filename: PathBuf::from("out.pdf"),
+        pages,
+        outline: Vec::new(),
+        bookmark: None,
+        path: Vec::new(),
+        adjust: Adjust::default(),
+    };
+    split_job(doc, job, None, name)
+}
+
+/// Runs a job, with links leaving it pointing to `outputs`, and loads the document that was written.
+fn split_job(
+    doc: Document,
+    job: SplitterJob,
+    outputs: Option<Vec<(Range<usize>, PathBuf)>>,
+    name: &str,
+) -> Document {
+    let dir = std::env::temp_dir().join(format!("pdf_splitter_{name}_{}", std::process::id()));
+    let filename = job.filename.to_owned();
+
+    let links = Links::new(&doc, outputs);
+    pdf::split(job, &Input::new(doc), &links, &dir).unwrap();
+
+    let out = Document::load(dir.join(filename)).unwrap();
+    fs::remove_dir_all(dir).unwrap();
+    out
+}
+
+fn get<'a>(doc: &'a Document, dict: &'a Dictionary, key: &[u8]) -> &'a Object {
+    doc.dereference(dict.get(key).unwrap()).unwrap().1
+}
+
+#[test]
+fn test_inherited_attributes() {
+    let doc = document(&["One", "Two", "Three"], false);
+    let out = split(doc, 0..3, "inherited");
+
+    let pages = out.page_iter().collect::<Vec<_>>();
+    assert_eq!(pages.len(), 3);
+
+    for (i, &page) in pages.iter().enumerate() {
+        let page = out.get_dictionary(page).unwrap();
+        let media_box = get(&out, page, b"MediaBox").as_array().unwrap();
+        let size = media_box
+            .iter()
+            .map(|x| x.as_i64().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(size, [0, 0, 300, 400]);
+        assert!(page.has(b"CropBox"));
+
+        let rotate = page.get(b"Rotate").and_then(Object::as_i64).ok();
+        assert_eq!(rotate, (i > 0).then_some(90));
+
+        let resources = get(&out, page, b"Resources").as_dict().unwrap();
+        let fonts = get(&out, resources, b"Font").as_dict().unwrap();
+        assert!(fonts.has(b"F1"));
+    }
+
+    assert!(out.extract_text(&[2]).unwrap().contains("Two"));
+}
+
+#[test]
+fn test_part_of_document() {
+    let doc = document(&["One", "Two", "Three", "Four"], false);
+    let out = split(doc, 2..4, "part");
+
+    assert_eq!(out.page_iter().count(), 2);
+    assert!(out.extract_text(&[1]).unwrap().contains("Three"));
+    assert!(out.extract_text(&[2]).unwrap().contains("Four"));
+}
+
+#[test]
+fn test_prune_resources() {
+    let doc = document(&["One", "Two"], false);
+    let out = split(doc, 0..2, "prune");
+
+    for page in out.page_iter() {
+        let page = out.get_dictionary(page).unwrap();
+        let resources = get(&out, page, b"Resources").as_dict().unwrap();
+        let fonts = get(&out, resources, b"Font").as_dict().unwrap();
+        assert!(fonts.has(b"F1"));
+        assert!(!fonts.has(b"F2"));
+    }
+
+    // The font used on both pages is only copied once
+    let fonts = out
+        .objects
+        .values()
+        .filter(|x| x.type_name().ok() == Some(b"Font".as_slice()))
+        .count();
+    assert_eq!(fonts, 1);
+}
+
+#[test]
+fn test_metadata() {
+    let out = split(document(&["One"], false), 0..1, "no_metadata");
+    assert!(!out.catalog().unwrap().has(b"Metadata"));
+
+    let out = split(document(&["One"], true), 0..1, "metadata");
+    let metadata = get(&out, out.catalog().unwrap(), b"Metadata");
+    assert!(metadata.as_stream().is_ok());
+}
+
+#[test]
+fn test_malformed_input() {
+    // An info dictionary written straight into the trailer is still copied
+    let mut doc = document(&["One"], false);
+    doc.trailer.set(
+        "Info",
+        dictionary! { "Title" => Object::string_literal("Direct") },
+    );
+    let out = split(doc, 0..1, "direct_info");
+    let info = get(&out, &out.trailer, b"Info").as_dict().unwrap();
+    assert_eq!(info.get(b"Title").unwrap().as_str().unwrap(), b"Direct");
+
+    // Without a catalog the job fails instead of panicking
+    let job = SplitterJob {
+        filename: PathBuf::from("out.pdf"),
+        pages: 0..1,
+        outline: Vec::new(),
+        bookmark: None,
+        path: Vec::new(),
+        adjust: Adjust::default(),
+    };
+    let mut input = Input::new(document(&["One"], false));
+    input.doc.trailer.remove(b"Root");
+    let links = Links::new(&input.doc, None);
+    let error = pdf::build(&job, &input, &links).err().unwrap();
+    assert_eq!(error.to_string(), "Reading the catalog");
+}
+
+#[test]
+fn test_config_rules() {
+    let config = r#"
+        [[rule]]
+        match = '^\d+ '
+        captures = '^(\d+) (.*)'
+        rename = 'Chapter $1 - $2'
+        dir = 'chapters'
+        after = 1
+
+        [[rule]]
+        match = '^Appendix'
+        skip = true
+
+        [[rule]]
+        match = '.*'
+        merge_shorter_than = 3
+    "#;
+    let args = Arc::new(Args::parse_from(["pdf_splitter", "in.pdf", "out"]));
+    let splitter = ConfigSplitter::new(config::parse(config).unwrap(), args);
+
+    let section = |name: &str| Section {
+        name: name.to_owned(),
+        ..Default::default()
+    };
+
+    let chapter = section("2 Getting Started");
+    assert_eq!(splitter.name(&chapter), "Chapter_2_-_Getting_Started");
+    assert_eq!(splitter.dir(&chapter), Some(PathBuf::from("chapters")));
+    assert!(splitter.should_split(&chapter));
+    assert_eq!(splitter.adjust(&chapter).after, 1);
+
+    assert!(!splitter.should_split(&section("Appendix A")));
+    // Names without a rule are still cleaned, sub-sections in nested mode never go through `should_split`
+    assert_eq!(splitter.name(&section("Appendix B: I/O")), "Appendix_B_IO");
+
+    let other = section("Index: Terms");
+    assert_eq!(splitter.name(&other), "Index_Terms");
+    assert_eq!(splitter.adjust(&other).merge_shorter_than, 3);
+
+    assert!(config::parse("[[rule]]\nmatch = '('").is_err());
+    assert!(config::parse("[[rule]]\nmatch = '.*'\nunknown = 1").is_err());
+}
+
+#[test]
+fn test_nested_jobs() {
+    let mut doc = document(&["Page"; 12], false);
+    add_outline(
+        &mut doc,
+        &[
+            ("1 Introduction", 0, 1),
+            ("2 ASN.1 structure handling", 0, 3),
+            ("ASN.1 syntax", 1, 4),
+            ("Deep", 2, 5),
+            ("Naming", 1, 6),
+            ("3 Utilities", 0, 7),
+            ("Invoking asn1Parser", 1, 7),
+            ("Invoking asn1Coding", 1, 8),
+            ("Appendix", 0, 10),
+        ],
+    );
+
+    // Only the numbered chapters are split, so the appendix ends up in the end slack
+    let args = Arc::new(Args::parse_from(["pdf_splitter", "in.pdf", "out", r"^\d"]));
+    let splitter = crate::ArgSplitter { args };
+    let files = |depth: &str| {
+        let depth = depth.parse().unwrap();
+        splitter::nested_jobs(&doc, &splitter, depth)
+            .unwrap()
+            .into_iter()
+            .map(|x| (x.filename.to_string_lossy().into_owned(), x.pages))
+            .collect::<Vec<_>>()
+    };
+
+    let asn = "02_2_ASN.1_structure_handling";
+    let utilities = "03_3_Utilities";
+    assert_eq!(
+        files("1"),
+        [
+            ("00_Title.pdf".to_owned(), 0..1),
+            ("01_1_Introduction.pdf".to_owned(), 1..3),
+            (format!("{asn}/00_2_ASN.1_structure_handling.pdf"), 3..4),
+            (format!("{asn}/01_ASN.1_syntax.pdf"), 4..6),
+            (format!("{asn}/02_Naming.pdf"), 6..7),
+            // No intro when the first sub-section starts on the chapter's first page
+            (format!("{utilities}/01_Invoking_asn1Parser.pdf"), 7..8),
+            (format!("{utilities}/02_Invoking_asn1Coding.pdf"), 8..10),
+            ("04_End.pdf".to_owned(), 10..12),
+        ]
+    );
+
+    let deep = files("-1")
+        .into_iter()
+        .filter(|x| x.0.starts_with(&format!("{asn}/01_")))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        deep,
+        [
+            (format!("{asn}/01_ASN.1_syntax/00_ASN.1_syntax.pdf"), 4..5),
+            (format!("{asn}/01_ASN.1_syntax/01_Deep.pdf"), 5..6),
+        ]
+    );
+
+    // Depth 0 keeps each chapter in one file
+    assert_eq!(files("0").len(), 5);
+
+    // The end slack is numbered after the last chapter, so the top level shares its width
+    let mut doc = document(&["Page"; 101], false);
+    let titles = (1..100).map(|i| format!("{i}")).collect::<Vec<_>>();
+    let mut outline = titles
+        .iter()
+        .enumerate()
+        .map(|(i, x)| (x.as_str(), 0, i + 1))
+        .collect::<Vec<_>>();
+    outline.push(("Appendix", 0, 100));
+    add_outline(&mut doc, &outline);
+    let files = splitter::nested_jobs(&doc, &splitter, Depth::All)
+        .unwrap()
+        .into_iter()
+        .map(|x| x.filename.to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    assert_eq!(files.len(), 101);
+    assert_eq!(files[..2], ["000_Title.pdf", "001_1.pdf"]);
+    assert_eq!(files[99..], ["099_99.pdf", "100_End.pdf"]);
+}
+
+#[test]
+fn test_ranges() {
+    let doc = document(&["Page"; 10], false);
+    let sections = |s: &str| {
+        s.parse::<Ranges>()
+            .unwrap()
+            .sections(&doc)
+            .map(|x| {
+                x.into_iter()
+                    .map(|x| (x.name, x.start..x.end))
+                    .collect::<Vec<_>>()
+            })
+            .map_err(|e| e.to_string())
+    };
+    let named = |name: &str, pages: Range<usize>| (name.to_owned(), pages);
+
+    let cases = [
+        ("1-4:Intro", vec![named("Intro", 0..4)]),
+        (
+            "1-4:Intro, 5-7 : Chapter 1,9",
+            vec![
+                named("Intro", 0..4),
+                named("Chapter 1", 4..7),
+                named("Page 9", 8..9),
+            ],
+        ),
+        (
+            "2-3,10",
+            vec![named("Pages 2-3", 1..3), named("Page 10", 9..10)],
+        ),
+        // Ranges can overlap and come in any order
+        ("6-8:B,1-7:A", vec![named("B", 5..8), named("A", 0..7)]),
+    ];
+    for (s, expected) in cases {
+        assert_eq!(sections(s).unwrap(), expected, "{s}");
+    }
+
+    for s in ["0", "0-2", "5-3", "a-b", "1-", "", "1,,2"] {
+        assert!(s.parse::<Ranges>().is_err(), "{s}");
+    }
+    assert!(sections("9-11").unwrap_err().contains("past the end"));
+}
+
+#[test]
+fn test_parse_size() {
+    let cases = [
+        ("500", 500),
+        ("500b", 500),
+        ("1K", 1 << 10),
+        ("1kb", 1 << 10),
+        (" 10 KB ", 10 << 10),
+        ("1.5MB", 3 << 19),
+        ("2g", 2 << 30),
+    ];
+    for (s, expected) in cases {
+        assert_eq!(source::parse_size(s), Ok(expected), "{s}");
+    }
+
+    for s in ["", "K", "0", "-1K", "1.5.2M", "10XB", "10 MiB"] {
+        assert!(source::parse_size(s).is_err(), "{s}");
+    }
+}
+
+#[test]
+fn test_chunks() {
+    let doc = document(&["Page"; 10], false);
+    let chunks = |size| {
+        Chunks(size)
+            .sections(&doc)
+            .unwrap()
+            .into_iter()
+            .map(|x| (x.name, x.start..x.end))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        chunks(4),
+        [
+            ("Part 1".to_owned(), 0..4),
+            ("Part 2".to_owned(), 4..8),
+            ("Part 3".to_owned(), 8..10),
+        ]
+    );
+    assert_eq!(chunks(10), [("Part 1".to_owned(), 0..10)]);
+    assert_eq!(chunks(20), [("Part 1".to_owned(), 0..10)]);
+    // Zero is taken as one page each
+    assert_eq!(chunks(0).len(), 10);
+}
+
+#[test]
+fn test_page_text() {
+    let doc = document(
+        &["Preface", "Chapter 1", "Text", "Chapter 1", "Chapter 2"],
+        false,
+    );
+    let sections = PageText(Regex::new(r"Chapter \d").unwrap())
+        .sections(&doc)
+        .unwrap()
+        .into_iter()
+        .map(|x| (x.name, x.start..x.end))
+        .collect::<Vec<_>>();
+
+    // A repeated heading gets a number instead of overwriting the first one
+    assert_eq!(
+        sections,
+        [
+            (String::new(), 0..1),
+            ("Chapter 1".to_owned(), 1..3),
+            ("Chapter 1 (2)".to_owned(), 3..4),
+            ("Chapter 2".to_owned(), 4..5),
+        ]
+    );
+}
+
+#[test]
+fn test_finish_adjustments() {
+    let job = |name: &str, pages: Range<usize>, adjust: Adjust| SplitterJob {
+        filename: PathBuf::from(name),
+        pages,
+        outline: Vec::new(),
+        bookmark: None,
+        path: Vec::new(),
+        adjust,
+    };
+    let merge = Adjust {
+        merge_shorter_than: 2,
+        ..Default::default()
+    };
+    let around = Adjust {
+        before: 1,
+        after: 5,
+        ..Default::default()
+    };
+
+    let jobs = vec![
+        job("a.pdf", 0..3, Adjust::default()),
+        job("b.pdf", 3..4, merge),
+        job("c/d.pdf", 4..5, merge),
+        job("e.pdf", 5..8, around),
+    ];
+    let pages = splitter::finish(jobs, &[], 10)
+        .into_iter()
+        .map(|x| (x.filename, x.pages))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        pages,
+        [
+            (PathBuf::from("a.pdf"), 0..4),
+            (PathBuf::from("c/d.pdf"), 4..5),
+            (PathBuf::from("e.pdf"), 4..10),
+        ]
+    );
+
+    // A short section isn't merged across a gap left by a skipped one
+    let jobs = vec![
+        job("a.pdf", 0..3, Adjust::default()),
+        job("c.pdf", 6..7, merge),
+    ];
+    let pages = splitter::finish(jobs, &[], 10)
+        .into_iter()
+        .map(|x| x.pages)
+        .collect::<Vec<_>>();
+    assert_eq!(pages, [0..3, 6..7]);
+}
+
+#[test]
+fn test_merge() {
+    let dir = std::env::temp_dir().join(format!("pdf_splitter_merge_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let one = dir.join("one.pdf");
+    let two = dir.join("two.pdf");
+    document(&["One", "Two"], false).save(&one).unwrap();
+    document(&["Three", "Four", "Five"], false)
+        .save(&two)
+        .unwrap();
+
+    let output = dir.join("out.pdf");
+    let args = MergeArgs {
+        inputs: vec![
+            one.to_string_lossy().parse().unwrap(),
+            format!("{}:2-3:Last", two.to_string_lossy())
+                .parse()
+                .unwrap(),
+        ],
+        output: output.to_owned(),
+        password: None,
+    };
+    merge::merge(&args).unwrap();
+
+    let out = Document::load(&output).unwrap();
+    fs::remove_dir_all(dir).unwrap();
+
+    assert_eq!(out.page_iter().count(), 4);
+    assert!(out.extract_text(&[4]).unwrap().contains("Five"));
+
+    let names = outline::read(&out)
+        .unwrap()
+        .iter()
+        .map(|x| (x.name(), x.page))
+        .collect::<Vec<_>>();
+    assert_eq!(names, [("one".to_owned(), 0), ("Last".to_owned(), 2)]);
+
+    // Both documents have the same font, so there's only one left
+    let fonts = out
+        .objects
+        .values()
+        .filter(|x| x.type_name().ok() == Some(b"Font".as_slice()))
+        .count();
+    assert_eq!(fonts, 1);
+}
+
+#[test]
+fn test_page_labels() {
+    let mut doc = document(&["One", "Two", "Three", "Four"], false);
+    let catalog = doc.catalog_mut().unwrap();
+    catalog.set(
+        "PageLabels",
+        dictionary! {
+            "Nums" => vec![
+                0.into(), dictionary! { "S" => "r" }.into(),
+                2.into(), dictionary! { "S" => "D", "St" => 5 }.into(),
+            ],
+        },
+    );
+
+    let out = split(doc, 1..4, "labels");
+    let labels = get(&out, out.catalog().unwrap(), b"PageLabels")
+        .as_dict()
+        .unwrap();
+    let nums = labels.get(b"Nums").unwrap().as_array().unwrap();
+    let labels = nums
+        .chunks_exact(2)
+        .map(|x| {
+            let label = x[1].as_dict().unwrap();
+            let style = std::str::from_utf8(label.get(b"S").unwrap().as_name().unwrap()).unwrap();
+            let start = label.get(b"St").and_then(Object::as_i64).unwrap_or(1);
+            (x[0].as_i64().unwrap(), style, start)
+        })
+        .collect::<Vec<_>>();
+
+    // Page ii, then 5 and 6
+    assert_eq!(labels, [(0, "r", 2), (1, "D", 5)]);
+}
+
+#[test]
+fn test_links_and_outline() {
+    // The second page links to the third (in the output), the fourth and the first (outside it)
+    let linked = || {
+        let mut doc = document(&["One", "Two", "Three", "Four"], false);
+        let pages = doc.page_iter().collect::<Vec<_>>();
+        let link = |key: &str, target: Object| {
+            Object::Dictionary(dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "Link",
+                "Rect" => vec![0.into(), 0.into(), 10.into(), 10.into()],
+                key => target,
+            })
+        };
+        let dest = |page: ObjectId| Object::Array(vec![page.into(), "Fit".into()]);
+        let annots = vec![
+            link("Dest", dest(pages[2])),
+            link(
+                "A",
+                dictionary! { "S" => "GoTo", "D" => dest(pages[3]) }.into(),
+            ),
+            link("Dest", dest(pages[0])),
+        ];
+        doc.get_dictionary_mut(pages[1])
+            .unwrap()
+            .set("Annots", annots);
+
+        add_outline(&mut doc, &[("Part", 0, 1), ("Sub", 1, 2), ("Other", 0, 3)]);
+        doc
+    };
+
+    let doc = linked();
+    let outline = outline::read(&doc).unwrap();
+    let job = || SplitterJob {
+        filename: PathBuf::from("part/out.pdf"),
+        pages: 1..3,
+        outline: outline::section(&outline, &(1..3)),
+        bookmark: None,
+        path: Vec::new(),
+        adjust: Adjust::default(),
+    };
+    let outputs = vec![
+        (0..1, PathBuf::from("start.pdf")),
+        (1..3, PathBuf::from("part/out.pdf")),
+        (3..4, PathBuf::from("end.pdf")),
+    ];
+    let out = split_job(doc, job(), Some(outputs), "links");
+    let pages = out.page_iter().collect::<Vec<_>>();
+
+    let first = out.get_dictionary(pages[0]).unwrap();
+    let annots = get(&out, first, b"Annots").as_array().unwrap();
+    let annots = annots
+        .iter()
+        .map(|x| out.dereference(x).unwrap().1.as_dict().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(annots.len(), 3);
+
+    // The link inside the output goes to the new page
+    let dest = annots[0].get(b"Dest").unwrap().as_array().unwrap();
+    assert_eq!(dest[0].as_reference().unwrap(), pages[1]);
+    assert!(!annots[0].has(b"A"));
+
+    // The others go to the file they were split into
+    let uri = |annot: &Dictionary| {
+        assert!(!annot.has(b"Dest"));
+        let action = annot.get(b"A").unwrap().as_dict().unwrap();
+        assert_eq!(action.get(b"S").unwrap().as_name().unwrap(), b"URI");
+        String::from_utf8(action.get(b"URI").unwrap().as_str().unwrap().to_vec()).unwrap()
+    };
+    assert_eq!(uri(annots[1]), "../end.pdf#page=1");
+    assert_eq!(uri(annots[2]), "../start.pdf#page=1");
+
+    // Only the section's own bookmarks are kept, pointing at the new pages
+    let catalog = out.catalog().unwrap();
+    let outlines_id = catalog.get(b"Outlines").unwrap().as_reference().unwrap();
+    let outlines = out.get_dictionary(outlines_id).unwrap();
+    assert_eq!(outlines.get(b"Count").unwrap().as_i64().unwrap(), 2);
+    let first = outlines.get(b"First").and_then(Object::as_reference);
+    assert_eq!(
+        first.ok(),
+        outlines.get(b"Last").and_then(Object::as_reference).ok()
+    );
+
+    let bookmark = |id: &Object| {
+        let id = id.as_reference().unwrap();
+        (id, out.get_dictionary(id).unwrap())
+    };
+    let (part_id, part) = bookmark(outlines.get(b"First").unwrap());
+    assert_eq!(part.get(b"Title").unwrap().as_str().unwrap(), b"Part");
+    assert_eq!(
+        part.get(b"Parent").unwrap().as_reference().unwrap(),
+        outlines_id
+    );
+    assert!(!part.has(b"Next"));
+    assert_eq!(part.get(b"Count").unwrap().as_i64().unwrap(), 1);
+    let dest = part.get(b"Dest").unwrap().as_array().unwrap();
+    assert_eq!(dest[0].as_reference().unwrap(), pages[0]);
+
+    let (_, sub) = bookmark(part.get(b"First").unwrap());
+    assert_eq!(sub.get(b"Title").unwrap().as_str().unwrap(), b"Sub");
+    assert_eq!(sub.get(b"Parent").unwrap().as_reference().unwrap(), part_id);
+    assert!(!sub.has(b"First") && !sub.has(b"Next"));
+    let dest = sub.get(b"Dest").unwrap().as_array().unwrap();
+    assert_eq!(dest[0].as_reference().unwrap(), pages[1]);
+
+    // Without other outputs, links leaving the section are removed
+    let out = split_job(linked(), job(), None, "no_links");
+    let first = out.get_dictionary(out.page_iter().next().unwrap()).unwrap();
+    assert_eq!(get(&out, first, b"Annots").as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_encrypted() {
+    let encrypted = |version: i64| {
+        let mut doc = document(&["Secret"], false);
+        let info = doc.add_object(dictionary! { "Title" => Object::string_literal("Hidden") });
+        doc.trailer.set("Info", info);
+        doc.trailer.set(
+            "ID",
+            vec![
+                Object::String(b"id".to_vec(), StringFormat::Hexadecimal),
+                Object::String(b"id".to_vec(), StringFormat::Hexadecimal),
+            ],
+        );
+
+        let filter: Arc<dyn CryptFilter> = match version {
+            4 => Arc::new(Aes128CryptFilter),
+            _ => Arc::new(Aes256CryptFilter),
+        };
+        let crypt_filters = BTreeMap::from([(b"StdCF".to_vec(), filter)]);
+        let (owner_password, user_password) = ("owner", "user");
+        let permissions = Permissions::default();
+        let state = match version {
+            2 => EncryptionVersion::V2 {
+                document: &doc,
+                owner_password,
+                user_password,
+                key_length: 128,
+                permissions,
+            },
+            4 => EncryptionVersion::V4 {
+                document: &doc,
+                encrypt_metadata: true,
+                crypt_filters,
+                stream_filter: b"StdCF".to_vec(),
+                string_filter: b"StdCF".to_vec(),
+                owner_password,
+                user_password,
+                permissions,
+            },
+            _ => EncryptionVersion::V5 {
+                encrypt_metadata: true,
+                crypt_filters,
+                file_encryption_key: &[7; 32],
+                stream_filter: b"StdCF".to_vec(),
+                string_filter: b"StdCF".to_vec(),
+                owner_password,
+                user_password,
+                permissions,
+            },
+        };
+        let state = EncryptionState::try_from(state).unwrap();
+        doc.encrypt(&state).unwrap();
+
+        let path = std::env::temp_dir().join(format!(
+            "pdf_splitter_encrypted_{version}_{}.pdf",
+            std::process::id()
+        ));
+        doc.save(&path).unwrap();
+        path
+    };
+
+    // RC4, AES-128 and AES-256, which can be opened with either password
+    for version in [2, 4, 5] {
+        let path = encrypted(version);
+        let error = |password| input::load(&path, password).unwrap_err().to_string();
+        assert!(error(None).contains("--password"));
+        assert_eq!(error(Some("wrong")), "Wrong password");
+
+        for password in ["user", "owner"] {
+            let out = input::load(&path, Some(password)).unwrap();
+            assert!(!out.is_encrypted());
+            let page = out.page_iter().next().unwrap();
+            let content = out.get_page_content(page).unwrap();
+            assert!(content.windows(6).any(|x| x == b"Secret"));
+            let info = get(&out, &out.trailer, b"Info").as_dict().unwrap();
+            assert_eq!(info.get(b"Title").unwrap().as_str().unwrap(), b"Hidden");
+        }
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use crate::Soon;
+
+/// Builds an `Arc` of a struct whose `Soon` fields point back to it, filling every `Soon` before returning.
+/// Kinda like [`Arc::new_cyclic`], but for as many fields as you want.
+///
+/// Normal fields go in the first block and each `Soon` field gets a closure in the `soon` block.
+/// The closures are given the finished `Arc` and run in the order they are listed.
+/// Because the struct literal is built by the macro, forgetting a field is a compile error
+/// instead of a panic (or segfault) on the first deref.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use soon::Soon;
+/// struct App {
+///     name: String,
+///     item: Soon<Item>,
+/// }
+///
+/// struct Item {
+///     app: Arc<App>,
+/// }
+///
+/// let app = soon::cyclic!(App {
+///     name: "app".to_owned(),
+/// } soon {
+///     item: |app| Item { app: app.clone() },
+/// });
+/// assert_eq!(app.item.app.name, "app");
+/// ```
+#[macro_export]
+macro_rules! cyclic {
+    (
+        $ty:ident { $($field:ident: $value:expr),* $(,)? }
+        soon { $($soon:ident: $make:expr),* $(,)? }
+    ) => {{
+        let this = ::std::sync::Arc::new($ty {
+            $($field: $value,)*
+            $($soon: $crate::Soon::empty(),)*
+        });
+        $($crate::cyclic::fill(&this, &this.$soon, $make);)*
+        this
+    }};
+}
+
+#[doc(hidden)]
+pub fn fill<T, U>(this: &Arc<T>, soon: &Soon<U>, make: impl FnOnce(&Arc<T>) -> U) {
+    soon.replace(make(this));
+}
@@ -4,19 +4,32 @@
 //! also its been too long since ive used unsafe for no reason.
 //! This is originally from my [radio-data project](https://github.com/Basicprogrammer10/radio-data/blob/master/src/misc/soon.rs).
 
-use std::{cell::UnsafeCell, mem::MaybeUninit, ops::Deref};
+use std::{
+    cell::UnsafeCell,
+    fmt::{self, Debug},
+    mem::MaybeUninit,
+    ops::Deref,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
-#[cfg(debug_assertions)]
+#[doc(hidden)]
+pub mod cyclic;
+#[cfg(any(debug_assertions, feature = "checked"))]
 mod safety;
+mod slots;
+mod sync;
 #[cfg(test)]
 mod test;
+pub use slots::SoonSlots;
+pub use sync::{SyncSoon, Wait};
 
 /// A *VERY UNSAFE* way to set values after creating a struct.
 /// Like a RefCell without the borrow checking.
 /// You are expected to use it properly.
 pub struct Soon<T> {
-    inner: MaybeUninit<UnsafeCell<T>>,
-    #[cfg(debug_assertions)]
+    inner: UnsafeCell<MaybeUninit<T>>,
+    filled: AtomicBool,
+    #[cfg(any(debug_assertions, feature = "checked"))]
     safety: safety::SoonSafety,
 }
 
@@ -25,44 +38,104 @@ impl<T> Soon<T> {
     /// If it is dereferenced at this point, in debug mode it will panic
     /// and in release mode you will get some sorta segfault.
     /// **(very unsafe)**
+    #[track_caller]
     pub fn empty() -> Self {
         Self {
-            inner: MaybeUninit::zeroed(),
-            #[cfg(debug_assertions)]
-            safety: safety::SoonSafety::default(),
+            inner: UnsafeCell::new(MaybeUninit::uninit()),
+            filled: AtomicBool::new(false),
+            #[cfg(any(debug_assertions, feature = "checked"))]
+            safety: safety::SoonSafety::new(),
         }
     }
 
     /// Replace whatever is in the `Soon` with a specified value.
-    /// Only call this once per soon object.
+    /// Only call this once per soon object (or once per [`Soon::take`]).
+    #[track_caller]
     pub fn replace(&self, val: T) {
-        #[cfg(debug_assertions)]
-        self.safety.on_replace();
+        #[cfg(any(debug_assertions, feature = "checked"))]
+        self.safety.on_replace(self.is_set());
 
         // SAFETY: nobody cares >:)
-        let cell = self.inner.as_ptr() as *mut T;
         unsafe {
-            cell.write(val);
+            (*self.inner.get()).write(val);
         }
+        self.filled.store(true, Ordering::Release);
+    }
+
+    /// Returns true if the `Soon` has been given a value.
+    pub fn is_set(&self) -> bool {
+        self.filled.load(Ordering::Acquire)
+    }
+
+    /// Gets a reference to the value, or `None` if it hasn't been given one yet.
+    pub fn get(&self) -> Option<&T> {
+        self.is_set().then(|| &**self)
+    }
+
+    /// Like [`Soon::replace`], but gives the value back instead of panicking if the `Soon` already has one.
+    #[track_caller]
+    pub fn try_replace(&self, val: T) -> Result<(), T> {
+        if self.is_set() {
+            return Err(val);
+        }
+
+        self.replace(val);
+        Ok(())
+    }
+
+    /// Moves the value out of the `Soon`, leaving it empty.
+    /// After this the `Soon` can be given a new value with [`Soon::replace`].
+    pub fn take(&mut self) -> Option<T> {
+        if !*self.filled.get_mut() {
+            return None;
+        }
+
+        *self.filled.get_mut() = false;
+        #[cfg(any(debug_assertions, feature = "checked"))]
+        self.safety.on_take();
+        // SAFETY: the value was written by `replace` and is now marked as empty so it wont be read again
+        Some(unsafe { self.inner.get_mut().assume_init_read() })
+    }
+
+    /// Drops the value in the `Soon` (if any), so it can be filled again.
+    pub fn reset(&mut self) {
+        self.take();
+    }
+
+    /// Consumes the `Soon`, returning its value if it had one.
+    pub fn into_inner(mut self) -> Option<T> {
+        self.take()
     }
 }
 
 impl<T> Deref for Soon<T> {
     type Target = T;
 
+    #[track_caller]
     fn deref(&self) -> &Self::Target {
-        #[cfg(debug_assertions)]
-        self.safety.on_deref();
+        #[cfg(any(debug_assertions, feature = "checked"))]
+        self.safety.on_deref(self.is_set());
 
-        let cell = UnsafeCell::raw_get(self.inner.as_ptr());
-        unsafe { &*cell }
+        unsafe { (*self.inner.get()).assume_init_ref() }
+    }
+}
+
+/// Shows the value (if any), and in debug builds where the `Soon` was created and filled.
+impl<T: Debug> Debug for Soon<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("Soon");
+        debug.field("value", &self.get());
+        #[cfg(any(debug_assertions, feature = "checked"))]
+        self.safety.debug_fields(&mut debug);
+        debug.finish()
     }
 }
 
 impl<T> Drop for Soon<T> {
     fn drop(&mut self) {
-        let cell = UnsafeCell::raw_get(self.inner.as_ptr());
-        unsafe { cell.drop_in_place() }
+        if *self.filled.get_mut() {
+            unsafe { self.inner.get_mut().assume_init_drop() }
+        }
     }
 }
 
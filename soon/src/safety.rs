@@ -1,40 +1,128 @@
+#[cfg(debug_assertions)]
+use std::backtrace::{Backtrace, BacktraceStatus};
 use std::{
+    fmt::{self, DebugStruct, Display},
     mem,
-    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    panic::Location,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
     thread,
 };
 
 pub struct SoonSafety {
     init_thread: AtomicUsize,
-    has_value: AtomicBool,
+    created: Origin,
+    filled: Mutex<Option<Origin>>,
 }
 
+/// Where something happened to a `Soon`.
+/// The backtrace is only captured if enabled with `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE`.
+#[cfg(debug_assertions)]
+struct Origin {
+    location: &'static Location<'static>,
+    backtrace: Backtrace,
+}
+
+/// Release builds with the `checked` feature only keep whether a `Soon` was filled, not where.
+#[cfg(not(debug_assertions))]
+struct Origin;
+
 impl SoonSafety {
-    pub fn on_replace(&self) {
+    #[track_caller]
+    pub fn new() -> Self {
+        Self {
+            init_thread: AtomicUsize::new(current_thread()),
+            created: Origin::capture(),
+            filled: Mutex::new(None),
+        }
+    }
+
+    #[track_caller]
+    pub fn on_replace(&self, has_value: bool) {
+        let location = Location::caller();
         if self.init_thread.load(Ordering::Relaxed) != current_thread() {
-            panic!("Tried to replace a `Soon` on different thread than it was created.")
+            panic!(
+                "Tried to replace a `Soon` on different thread than it was created.\n  replaced at {location} on {:?}\n  created at {}",
+                thread::current().id(),
+                self.created
+            )
         }
 
-        if self.has_value.load(Ordering::Relaxed) {
-            panic!("Tried to replace a `Soon` that already had a value.");
+        let mut filled = self.filled.lock().unwrap();
+        if has_value {
+            let first = filled.as_ref().map(|x| x.to_string()).unwrap_or_default();
+            drop(filled);
+            panic!(
+                "Tried to replace a `Soon` that already had a value.\n  replaced again at {location}\n  first filled at {first}\n  created at {}",
+                self.created
+            );
         }
 
-        self.has_value.store(true, Ordering::Relaxed);
+        *filled = Some(Origin::capture());
+    }
+
+    pub fn on_take(&self) {
+        *self.filled.lock().unwrap() = None;
+    }
+
+    #[track_caller]
+    pub fn on_deref(&self, has_value: bool) {
+        if !has_value {
+            panic!(
+                "A `Soon` was dereferenced before being givin a value.\n  dereferenced at {}\n  created at {}",
+                Location::caller(),
+                self.created
+            );
+        }
     }
 
-    pub fn on_deref(&self) {
-        if !self.has_value.load(Ordering::Relaxed) {
-            panic!("A `Soon` was dereferenced before being givin a value.");
+    #[cfg(debug_assertions)]
+    pub fn debug_fields(&self, debug: &mut DebugStruct) {
+        debug.field("created", &format_args!("{}", self.created.location));
+        if let Some(filled) = &*self.filled.lock().unwrap() {
+            debug.field("filled", &format_args!("{}", filled.location));
         }
     }
+
+    #[cfg(not(debug_assertions))]
+    pub fn debug_fields(&self, _debug: &mut DebugStruct) {}
 }
 
-impl Default for SoonSafety {
-    fn default() -> Self {
+#[cfg(debug_assertions)]
+impl Origin {
+    #[track_caller]
+    fn capture() -> Self {
         Self {
-            init_thread: AtomicUsize::new(current_thread()),
-            has_value: Default::default(),
+            location: Location::caller(),
+            backtrace: Backtrace::capture(),
+        }
+    }
+}
+
+#[cfg(debug_assertions)]
+impl Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.location)?;
+        if self.backtrace.status() == BacktraceStatus::Captured {
+            write!(f, "\n{}", self.backtrace)?;
         }
+        Ok(())
+    }
+}
+
+#[cfg(not(debug_assertions))]
+impl Origin {
+    fn capture() -> Self {
+        Self
+    }
+}
+
+#[cfg(not(debug_assertions))]
+impl Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "an unknown location (only tracked in debug builds)")
     }
 }
 
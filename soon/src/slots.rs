@@ -0,0 +1,102 @@
+use std::{
+    fmt::{self, Debug},
+    ops::Index,
+};
+
+use crate::Soon;
+
+/// A fixed number of `Soon`s that can be filled in any order.
+/// Useful for graphs where every node needs to point back at something that doesn't exist yet.
+/// The slots are allocated up front and never move, so references to filled values stay valid.
+/// Each slot is a normal `Soon`, so all the same debug checks apply.
+pub struct SoonSlots<T> {
+    slots: Box<[Soon<T>]>,
+}
+
+impl<T> SoonSlots<T> {
+    /// Create `len` empty slots.
+    #[track_caller]
+    pub fn new(len: usize) -> Self {
+        let mut slots = Vec::with_capacity(len);
+        for _ in 0..len {
+            slots.push(Soon::empty());
+        }
+
+        Self {
+            slots: slots.into_boxed_slice(),
+        }
+    }
+
+    /// Give the slot at `idx` its value.
+    /// Only call this once per slot, just like [`Soon::replace`].
+    #[track_caller]
+    pub fn fill(&self, idx: usize, val: T) {
+        self.slots[idx].replace(val);
+    }
+
+    /// Like [`SoonSlots::fill`], but gives the value back if the slot was already filled.
+    #[track_caller]
+    pub fn try_fill(&self, idx: usize, val: T) -> Result<(), T> {
+        self.slots[idx].try_replace(val)
+    }
+
+    /// Gets the value in a slot, or `None` if it is out of bounds or hasn't been filled yet.
+    pub fn get(&self, idx: usize) -> Option<&T> {
+        self.slots.get(idx).and_then(Soon::get)
+    }
+
+    /// Returns true if the slot at `idx` has been filled.
+    pub fn is_set(&self, idx: usize) -> bool {
+        self.get(idx).is_some()
+    }
+
+    /// The total number of slots, filled or not.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Iterates over the values of only the filled slots.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().filter_map(Soon::get)
+    }
+
+    /// The indices of every slot that hasn't been filled yet.
+    pub fn unfilled(&self) -> Vec<usize> {
+        (0..self.len()).filter(|&i| !self.is_set(i)).collect()
+    }
+
+    /// In debug builds or with the `checked` feature, panics with the indices of any slots that were never filled.
+    /// Does nothing otherwise.
+    #[track_caller]
+    pub fn check_filled(&self) {
+        #[cfg(any(debug_assertions, feature = "checked"))]
+        {
+            let unfilled = self.unfilled();
+            if !unfilled.is_empty() {
+                panic!("`SoonSlots` has slots that were never filled: {unfilled:?}");
+            }
+        }
+    }
+}
+
+impl<T> Index<usize> for SoonSlots<T> {
+    type Output = T;
+
+    #[track_caller]
+    fn index(&self, idx: usize) -> &Self::Output {
+        &self.slots[idx]
+    }
+}
+
+impl<T: Debug> Debug for SoonSlots<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SoonSlots")
+            .field("slots", &self.slots)
+            .field("unfilled", &self.unfilled())
+            .finish()
+    }
+}
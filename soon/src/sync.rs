@@ -0,0 +1,162 @@
+use std::{
+    cell::UnsafeCell,
+    future::Future,
+    mem::MaybeUninit,
+    ops::Deref,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Condvar, Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+
+const EMPTY: u8 = 0;
+const WRITING: u8 = 1;
+const READY: u8 = 2;
+
+/// A `Soon` that can actually be shared between threads.
+/// Any thread can give it its value (exactly once) and other threads can wait for it to show up,
+/// either by blocking with [`SyncSoon::wait`] or by awaiting [`SyncSoon::wait_async`].
+pub struct SyncSoon<T> {
+    inner: UnsafeCell<MaybeUninit<T>>,
+    state: AtomicU8,
+    wakers: Mutex<Vec<Waker>>,
+    condvar: Condvar,
+}
+
+/// Future returned by [`SyncSoon::wait_async`].
+pub struct Wait<'a, T> {
+    soon: &'a SyncSoon<T>,
+}
+
+impl<T> SyncSoon<T> {
+    /// Create a new `SyncSoon` without its value.
+    pub fn empty() -> Self {
+        Self {
+            inner: UnsafeCell::new(MaybeUninit::uninit()),
+            state: AtomicU8::new(EMPTY),
+            wakers: Mutex::new(Vec::new()),
+            condvar: Condvar::new(),
+        }
+    }
+
+    /// Gives the `SyncSoon` its value and wakes up anything waiting on it.
+    /// Panics if it already has a value.
+    pub fn replace(&self, val: T) {
+        if self.try_replace(val).is_err() {
+            panic!("Tried to replace a `SyncSoon` that already had a value.");
+        }
+    }
+
+    /// Like [`SyncSoon::replace`], but gives the value back if the `SyncSoon` was already filled.
+    pub fn try_replace(&self, val: T) -> Result<(), T> {
+        if self
+            .state
+            .compare_exchange(EMPTY, WRITING, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(val);
+        }
+
+        // SAFETY: we are the only thread that got to move the state to WRITING
+        // and nobody reads the value until its READY
+        unsafe {
+            (*self.inner.get()).write(val);
+        }
+        self.state.store(READY, Ordering::Release);
+
+        // Taking the lock makes sure no waiter can be between checking the state and going to sleep
+        let wakers = std::mem::take(&mut *self.wakers.lock().unwrap());
+        self.condvar.notify_all();
+        wakers.into_iter().for_each(Waker::wake);
+
+        Ok(())
+    }
+
+    /// Returns true if the `SyncSoon` has been given a value.
+    pub fn is_set(&self) -> bool {
+        self.state.load(Ordering::Acquire) == READY
+    }
+
+    /// Gets a reference to the value, or `None` if it hasn't been given one yet.
+    pub fn get(&self) -> Option<&T> {
+        // SAFETY: once READY the value is never written again
+        self.is_set()
+            .then(|| unsafe { (*self.inner.get()).assume_init_ref() })
+    }
+
+    /// Blocks the current thread until the `SyncSoon` has a value.
+    pub fn wait(&self) -> &T {
+        if let Some(val) = self.get() {
+            return val;
+        }
+
+        let mut wakers = self.wakers.lock().unwrap();
+        while !self.is_set() {
+            wakers = self.condvar.wait(wakers).unwrap();
+        }
+
+        drop(wakers);
+        self.get().unwrap()
+    }
+
+    /// Returns a future that resolves once the `SyncSoon` has a value.
+    pub fn wait_async(&self) -> Wait<'_, T> {
+        Wait { soon: self }
+    }
+
+    /// Consumes the `SyncSoon`, returning its value if it had one.
+    pub fn into_inner(mut self) -> Option<T> {
+        if *self.state.get_mut() != READY {
+            return None;
+        }
+
+        *self.state.get_mut() = EMPTY;
+        Some(unsafe { self.inner.get_mut().assume_init_read() })
+    }
+}
+
+impl<'a, T> Future for Wait<'a, T> {
+    type Output = &'a T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let soon = self.soon;
+        if let Some(val) = soon.get() {
+            return Poll::Ready(val);
+        }
+
+        let mut wakers = soon.wakers.lock().unwrap();
+        if let Some(val) = soon.get() {
+            return Poll::Ready(val);
+        }
+
+        if !wakers.iter().any(|x| x.will_wake(cx.waker())) {
+            wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+impl<T> Deref for SyncSoon<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        match self.get() {
+            Some(val) => val,
+            None => panic!("A `SyncSoon` was dereferenced before being given a value."),
+        }
+    }
+}
+
+impl<T> Drop for SyncSoon<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == READY {
+            unsafe { self.inner.get_mut().assume_init_drop() }
+        }
+    }
+}
+
+// Unlike `Soon`, this one actually is thread safe.
+unsafe impl<T: Send> Send for SyncSoon<T> {}
+unsafe impl<T: Send + Sync> Sync for SyncSoon<T> {}
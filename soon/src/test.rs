@@ -1,6 +1,16 @@
-use std::{sync::Arc, thread};
+use std::{
+    future::Future,
+    pin::pin,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+};
 
-use super::Soon;
+use super::{Soon, SoonSlots, SyncSoon};
 
 #[test]
 fn test_basic() {
@@ -21,6 +31,29 @@ fn test_basic() {
     assert_eq!(Arc::into_raw(app.item.app.clone()), Arc::into_raw(app));
 }
 
+#[test]
+fn test_cyclic() {
+    struct App {
+        name: &'static str,
+        item: Soon<Item>,
+        other: Soon<Item>,
+    }
+
+    struct Item {
+        app: Arc<App>,
+    }
+
+    let app = crate::cyclic!(App {
+        name: "app",
+    } soon {
+        item: |app| Item { app: app.clone() },
+        other: |app| Item { app: app.item.app.clone() },
+    });
+
+    assert!(app.item.is_set() && app.other.is_set());
+    assert_eq!(app.other.app.name, "app");
+}
+
 #[test]
 #[should_panic]
 #[cfg(debug_assertions)]
@@ -36,3 +69,162 @@ fn test_no_value_drop() {
     let soon: Soon<u32> = Soon::empty();
     drop(soon);
 }
+
+#[test]
+fn test_checked() {
+    let soon: Soon<u32> = Soon::empty();
+    assert!(!soon.is_set());
+    assert_eq!(soon.get(), None);
+
+    assert_eq!(soon.try_replace(5), Ok(()));
+    assert_eq!(soon.try_replace(6), Err(6));
+    assert!(soon.is_set());
+    assert_eq!(soon.get(), Some(&5));
+}
+
+#[test]
+fn test_drop() {
+    let value = Rc::new(());
+
+    let soon: Soon<Rc<()>> = Soon::empty();
+    drop(soon);
+
+    let soon: Soon<Rc<()>> = Soon::empty();
+    soon.replace(value.clone());
+    assert_eq!(Rc::strong_count(&value), 2);
+    drop(soon);
+    assert_eq!(Rc::strong_count(&value), 1);
+}
+
+#[test]
+fn test_take() {
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    struct Counter;
+    impl Drop for Counter {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let mut soon: Soon<Counter> = Soon::empty();
+    assert!(soon.take().is_none());
+
+    soon.replace(Counter);
+    let taken = soon.take();
+    assert!(taken.is_some() && !soon.is_set());
+    drop(taken);
+    assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+
+    soon.replace(Counter);
+    soon.reset();
+    assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+
+    soon.replace(Counter);
+    assert!(soon.into_inner().is_some());
+    assert_eq!(DROPS.load(Ordering::Relaxed), 3);
+}
+
+#[test]
+fn test_sync_wait() {
+    let soon: SyncSoon<u32> = SyncSoon::empty();
+    thread::scope(|s| {
+        let waiter = s.spawn(|| *soon.wait());
+        s.spawn(|| soon.replace(5));
+        assert_eq!(waiter.join().unwrap(), 5);
+    });
+
+    assert_eq!(soon.try_replace(6), Err(6));
+    assert_eq!(soon.into_inner(), Some(5));
+}
+
+#[test]
+fn test_sync_wait_async() {
+    struct ThreadWaker(Thread);
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let soon: SyncSoon<&str> = SyncSoon::empty();
+    thread::scope(|s| {
+        s.spawn(|| soon.replace("hello"));
+
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = pin!(soon.wait_async());
+        let val = loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(val) => break val,
+                Poll::Pending => thread::park(),
+            }
+        };
+        assert_eq!(*val, "hello");
+    });
+}
+
+#[test]
+#[should_panic(expected = "created at soon/src/test.rs")]
+#[cfg(debug_assertions)]
+fn test_deref_location() {
+    let soon: Soon<u32> = Soon::empty();
+    let _ = *soon;
+}
+
+#[test]
+#[cfg(debug_assertions)]
+fn test_debug() {
+    let soon: Soon<u32> = Soon::empty();
+    assert!(format!("{soon:?}").starts_with("Soon { value: None, created: soon/src/test.rs"));
+
+    soon.replace(5);
+    let debug = format!("{soon:?}");
+    assert!(debug.starts_with("Soon { value: Some(5)") && debug.contains("filled:"));
+}
+
+#[test]
+fn test_slots() {
+    struct Graph {
+        nodes: SoonSlots<Node>,
+    }
+
+    struct Node {
+        graph: Arc<Graph>,
+        parent: Option<usize>,
+    }
+
+    let graph = Arc::new(Graph {
+        nodes: SoonSlots::new(3),
+    });
+    graph.nodes.fill(
+        2,
+        Node {
+            graph: graph.clone(),
+            parent: Some(0),
+        },
+    );
+    graph.nodes.fill(
+        0,
+        Node {
+            graph: graph.clone(),
+            parent: None,
+        },
+    );
+
+    assert_eq!(graph.nodes.unfilled(), vec![1]);
+    assert_eq!(graph.nodes.iter().count(), 2);
+    assert!(graph.nodes.get(1).is_none());
+
+    let parent = graph.nodes[2].parent.unwrap();
+    assert!(graph.nodes[parent].graph.nodes.is_set(2));
+}
+
+#[test]
+#[should_panic(expected = "never filled: [0, 2]")]
+#[cfg(any(debug_assertions, feature = "checked"))]
+fn test_slots_unfilled() {
+    let slots = SoonSlots::new(3);
+    slots.fill(1, ());
+    slots.check_filled();
+}
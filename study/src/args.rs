@@ -0,0 +1,161 @@
+use std::path::PathBuf;
+
+use clap::{builder::NonEmptyStringValueParser, Args as ClapArgs, Parser, Subcommand, ValueEnum};
+
+use study::set::{Mode, Scope};
+
+#[derive(Parser)]
+#[clap(
+    name = "study",
+    version = env!("CARGO_PKG_VERSION"),
+    about = "Flashcards in the terminal",
+    args_conflicts_with_subcommands = true,
+)]
+pub struct Args {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+    #[clap(flatten)]
+    pub set: SetArgs,
+    #[clap(flatten)]
+    pub study: StudyArgs,
+    /// How to answer the cards.
+    #[clap(long, short, value_enum, default_value_t = ModeArg::Choice)]
+    pub mode: ModeArg,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Make a printable practice test and answer key out of a set.
+    Test(TestArgs),
+    /// Show the hardest cards, accuracy over time and how much of each deck is mastered.
+    Stats(StatsArgs),
+    /// Study in the browser instead, the mode can be picked on the page.
+    Serve(ServeArgs),
+}
+
+/// Where to load a set from.
+#[derive(ClapArgs)]
+pub struct SetArgs {
+    /// The set to study, or a directory of decks to pick one from.
+    /// Can be a Quizlet export, a CSV file or an Anki `.apkg` deck.
+    #[clap(default_value = "data.txt")]
+    pub set: PathBuf,
+    /// Only use cards with this tag, can be given more than once.
+    #[clap(long, short)]
+    pub tag: Vec<String>,
+    /// Swap the question and answer of every card.
+    #[clap(long, short)]
+    pub reverse: bool,
+    /// Separator between the term and definition in Quizlet exports.
+    #[clap(long, default_value = "\\t", value_parser = NonEmptyStringValueParser::new())]
+    pub term_separator: String,
+    /// Separator between rows in Quizlet exports.
+    #[clap(long, default_value = "\\n", value_parser = NonEmptyStringValueParser::new())]
+    pub row_separator: String,
+    /// Separator between the definition and the card's tags in Quizlet exports.
+    /// Tags are only read from Quizlet exports when this is set.
+    #[clap(long, value_parser = NonEmptyStringValueParser::new())]
+    pub tag_separator: Option<String>,
+}
+
+/// How a session is run, for both the terminal and the browser.
+#[derive(ClapArgs)]
+pub struct StudyArgs {
+    /// Start a new session instead of resuming the last one.
+    #[clap(long)]
+    pub restart: bool,
+    /// Number of cards in each learn round.
+    #[clap(long, default_value_t = 10)]
+    pub section_size: usize,
+    /// Where wrong answers for multiple choice questions are picked from.
+    #[clap(long, value_enum, default_value_t = ScopeArg::Set)]
+    pub distractors: ScopeArg,
+    /// Seed for shuffling and picking options, to make a session reproducible.
+    #[clap(long)]
+    pub seed: Option<u64>,
+    /// Splits a card's answer into multiple accepted answers in write mode.
+    #[clap(long, default_value = ";", value_parser = NonEmptyStringValueParser::new())]
+    pub delimiter: String,
+}
+
+#[derive(ClapArgs)]
+pub struct TestArgs {
+    #[clap(flatten)]
+    pub set: SetArgs,
+    /// Number of questions on the test, split between the sections.
+    #[clap(long, short, default_value_t = 20)]
+    pub questions: usize,
+    /// Seed for picking and shuffling questions, the same seed makes the same test.
+    #[clap(long)]
+    pub seed: Option<u64>,
+    /// Directory to write the test and answer key to.
+    #[clap(long, short, default_value = ".")]
+    pub output: PathBuf,
+}
+
+#[derive(ClapArgs)]
+pub struct StatsArgs {
+    #[clap(flatten)]
+    pub set: SetArgs,
+    /// Number of hardest cards to show.
+    #[clap(long, default_value_t = 10)]
+    pub hardest: usize,
+    /// Also write every review to a file, as JSON if it ends in `.json` or CSV otherwise.
+    #[clap(long)]
+    pub export: Option<PathBuf>,
+}
+
+#[derive(ClapArgs)]
+pub struct ServeArgs {
+    #[clap(flatten)]
+    pub set: SetArgs,
+    #[clap(flatten)]
+    pub study: StudyArgs,
+    /// Address to listen on, use `0.0.0.0` to let other devices on the network connect.
+    #[clap(long, default_value = "127.0.0.1")]
+    pub host: String,
+    #[clap(long, short, default_value_t = 8080)]
+    pub port: u16,
+}
+
+/// [`Mode`] as a command line option.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ModeArg {
+    /// Pick the answer out of four options.
+    Choice,
+    /// Type out the answer.
+    Write,
+    /// Flip the card over and say if you knew it.
+    Flashcard,
+}
+
+/// [`Scope`] as a command line option.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ScopeArg {
+    /// Any card in the set.
+    Set,
+    /// Only cards in the current learn round.
+    Section,
+    /// Only cards that share a tag with the current card.
+    Tag,
+}
+
+impl From<ModeArg> for Mode {
+    fn from(mode: ModeArg) -> Self {
+        match mode {
+            ModeArg::Choice => Mode::Choice,
+            ModeArg::Write => Mode::Write,
+            ModeArg::Flashcard => Mode::Flashcard,
+        }
+    }
+}
+
+impl From<ScopeArg> for Scope {
+    fn from(scope: ScopeArg) -> Self {
+        match scope {
+            ScopeArg::Set => Scope::Set,
+            ScopeArg::Section => Scope::Section,
+            ScopeArg::Tag => Scope::Tag,
+        }
+    }
+}
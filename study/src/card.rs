@@ -1,42 +1,100 @@
-use std::str::FromStr;
+use std::fmt;
 
-#[derive(Clone, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+
+use crate::{schedule::Schedule, set::Mode};
+
+#[derive(Clone, PartialEq)]
 pub struct Card {
     pub question: String,
     pub answer: String,
+    /// Used to study only part of a set, see [`Card::has_tag`].
+    pub tags: Vec<String>,
     pub status: CardStatus,
+    /// Correct answers in a row in the current learn round.
+    pub streak: u32,
+    pub schedule: Schedule,
+    pub reviews: Vec<Review>,
 }
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CardStatus {
     New,
     Learning,
     Mastered,
 }
 
-impl FromStr for Card {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.splitn(2, '\t');
-        let answer = parts.next().ok_or(())?.to_string();
-        let question = parts.next().ok_or(())?.to_string();
+/// One answer given for a card.
+/// Reviews from before the latency and mode were recorded have them as `None`.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Review {
+    pub time: u64,
+    pub correct: bool,
+    /// Milliseconds from the question being shown to it being answered.
+    #[serde(default)]
+    pub latency: Option<u64>,
+    #[serde(default)]
+    pub mode: Option<Mode>,
+}
 
-        Ok(Self {
-            question,
-            answer,
+impl Card {
+    pub fn new(question: &str, answer: &str) -> Self {
+        Self {
+            question: question.to_owned(),
+            answer: answer.to_owned(),
+            tags: Vec::new(),
             status: CardStatus::New,
-        })
+            streak: 0,
+            schedule: Schedule::default(),
+            reviews: Vec::new(),
+        }
+    }
+
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Swap the question and answer, for studying a set in the other direction.
+    /// Reversed cards have their own progress, as the key changes too.
+    pub fn reverse(&mut self) {
+        std::mem::swap(&mut self.question, &mut self.answer);
+    }
+
+    /// Checks if the card has any of the tags, ignoring case.
+    /// With no tags to look for every card matches.
+    pub fn has_tag(&self, tags: &[String]) -> bool {
+        tags.is_empty()
+            || self
+                .tags
+                .iter()
+                .any(|x| tags.iter().any(|y| x.eq_ignore_ascii_case(y)))
+    }
+
+    /// Used to match a card up with its saved progress.
+    pub fn key(&self) -> String {
+        format!("{}\t{}", self.answer, self.question)
+    }
+
+    /// Log an answer to this card.
+    /// Only the first answer of a session counts towards the schedule, so having
+    /// to retry a missed card doesn't make it look well known.
+    pub fn review(&mut self, review: Review) {
+        if self.status == CardStatus::New {
+            let quality = if review.correct { 4 } else { 1 };
+            self.schedule.review(quality, review.time);
+        }
+
+        self.reviews.push(review);
     }
 }
 
-impl ToString for CardStatus {
-    fn to_string(&self) -> String {
-        match self {
+impl fmt::Display for CardStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
             CardStatus::New => "NEW",
             CardStatus::Learning => "LEARNING",
             CardStatus::Mastered => "MASTERED",
-        }
-        .to_string()
+        })
     }
 }
@@ -0,0 +1,125 @@
+use std::fs;
+
+use afire::Status;
+use clap::Parser;
+use rand::{rngs::StdRng, SeedableRng};
+use serde_json::json;
+use study::{
+    card::{Card, Review},
+    set::{Mode, Options, Scope, Set},
+};
+
+use crate::{
+    args::Args,
+    stats,
+    web::{self, App},
+};
+
+fn set(answers: &[&str]) -> Set {
+    let cards = answers
+        .iter()
+        .enumerate()
+        .map(|(i, x)| Card::new(&format!("Question {i}"), x))
+        .collect();
+    let options = Options {
+        section_size: 10,
+        distractors: Scope::Set,
+        seed: None,
+        tags: Vec::new(),
+        reverse: false,
+    };
+    Set::new("test", cards, options, StdRng::seed_from_u64(0))
+}
+
+#[test]
+fn test_web_stale_key() {
+    let set = set(&["a", "b"]);
+    let first = set.get_current_card().unwrap().key();
+    let app = App::new(set, ";");
+
+    let grade = |key: &str| {
+        let body = json!({ "key": key, "input": "a" }).to_string();
+        web::grade_answer(&app, body.as_bytes()).status
+    };
+    let mark = |key: &str| {
+        let body = json!({ "key": key, "correct": true, "latency": 500, "mode": "choice" });
+        web::mark_answer(&app, body.to_string().as_bytes()).status
+    };
+
+    assert_eq!(grade("nope"), Status::Conflict);
+    assert_eq!(mark("nope"), Status::Conflict);
+    assert_eq!(web::mark_answer(&app, b"{}").status, Status::BadRequest);
+
+    // Once the card is answered, a second mark for it (another tab, a double click) is rejected
+    assert_eq!(grade(&first), Status::Ok);
+    assert_eq!(mark(&first), Status::Ok);
+    assert_eq!(mark(&first), Status::Conflict);
+    assert_eq!(grade(&first), Status::Conflict);
+}
+
+#[test]
+fn test_export() {
+    let review = |time, correct, mode: Option<Mode>| Review {
+        time,
+        correct,
+        latency: mode.map(|_| 1500),
+        mode,
+    };
+    let mut latin = set(&["amo", "video"]);
+    latin.cards[0].tags = vec!["verb".to_owned(), "1st".to_owned()];
+    latin.cards[0].review(review(30, true, Some(Mode::Write)));
+    latin.cards[1].review(review(10, false, None));
+    let mut greek = set(&["λόγος"]);
+    greek.name = "greek".to_owned();
+    greek.cards[0].review(review(20, true, Some(Mode::Choice)));
+
+    let dir = std::env::temp_dir().join(format!("study_export_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let sets = [latin, greek];
+
+    // Every review of every deck, oldest first
+    let path = dir.join("log.json");
+    stats::export(&sets, &path).unwrap();
+    let log = serde_json::from_slice::<serde_json::Value>(&fs::read(&path).unwrap()).unwrap();
+    assert_eq!(
+        log,
+        json!([
+            { "deck": "test", "question": "Question 1", "answer": "video", "tags": "", "time": 10, "correct": false, "latency_ms": null, "mode": null },
+            { "deck": "greek", "question": "Question 0", "answer": "λόγος", "tags": "", "time": 20, "correct": true, "latency_ms": 1500, "mode": "choice" },
+            { "deck": "test", "question": "Question 0", "answer": "amo", "tags": "verb 1st", "time": 30, "correct": true, "latency_ms": 1500, "mode": "write" },
+        ])
+    );
+
+    // Anything else is CSV with a header
+    let path = dir.join("log.csv");
+    stats::export(&sets, &path).unwrap();
+    let log = fs::read_to_string(&path).unwrap();
+    let lines = log.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            "deck,question,answer,tags,time,correct,latency_ms,mode",
+            "test,Question 1,video,,10,false,,",
+            "greek,Question 0,λόγος,,20,true,1500,choice",
+            "test,Question 0,amo,verb 1st,30,true,1500,write",
+        ]
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_empty_separators() {
+    let parse = |args: &[&str]| Args::try_parse_from(["study"].iter().chain(args));
+    assert!(parse(&[]).is_ok());
+    assert!(parse(&["--delimiter", ","]).is_ok());
+    for arg in [
+        "--delimiter",
+        "--term-separator",
+        "--row-separator",
+        "--tag-separator",
+    ] {
+        assert!(parse(&[arg, ""]).is_err(), "{arg} accepted an empty value");
+    }
+    assert!(parse(&["serve", "--delimiter", ""]).is_err());
+}
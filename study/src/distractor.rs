@@ -0,0 +1,103 @@
+use std::collections::HashSet;
+
+use rand::{seq::SliceRandom, Rng};
+
+use crate::grade::normalize;
+
+/// Number of options in a multiple choice question, if there are enough answers to pick from.
+pub const CHOICES: usize = 4;
+/// With less options than this a true/false question is asked instead.
+pub const MIN_CHOICES: usize = 3;
+/// Distractors are picked at random from this many of the most similar answers,
+/// so the same card doesn't always get the same options.
+const POOL_SIZE: usize = 8;
+
+/// Pick up to three wrong answers out of `answers` that look like `answer` and shuffle in the right one.
+/// Small sets can end up with less than four options.
+pub fn options<'a>(
+    answer: &str,
+    answers: impl Iterator<Item = &'a str>,
+    rng: &mut impl Rng,
+) -> Vec<String> {
+    let mut candidates = answers.filter(|&x| x != answer).collect::<Vec<_>>();
+    candidates.sort();
+    candidates.dedup();
+
+    let mut options = pick(answer, &candidates, CHOICES - 1, rng);
+    options.push(answer.to_owned());
+    options.shuffle(rng);
+    options
+}
+
+/// Pick up to `count` wrong answers that look like `answer`.
+/// Candidates are scored on how close they are in length, format and vocabulary.
+pub fn pick(answer: &str, candidates: &[&str], count: usize, rng: &mut impl Rng) -> Vec<String> {
+    let mut scored = candidates
+        .iter()
+        .map(|x| (similarity(answer, x), *x))
+        .collect::<Vec<_>>();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.truncate(POOL_SIZE.max(count));
+
+    scored
+        .choose_multiple(rng, count)
+        .map(|x| x.1.to_owned())
+        .collect()
+}
+
+/// How alike two answers are, from 0 to 1.
+fn similarity(a: &str, b: &str) -> f32 {
+    let (a_len, b_len) = (a.chars().count() as f32, b.chars().count() as f32);
+    let length = 1.0 - (a_len - b_len).abs() / a_len.max(b_len).max(1.0);
+
+    let (a_format, b_format) = (format(a), format(b));
+    let format = a_format
+        .iter()
+        .zip(b_format.iter())
+        .filter(|(a, b)| a == b)
+        .count() as f32
+        / a_format.len() as f32;
+
+    length * 0.4 + format * 0.3 + vocabulary(a, b) * 0.3
+}
+
+/// Things that make an answer stand out from the others,
+/// like a verb's principal parts `desilio, desilire, desilui` or a noun's `scapha, -ae f.`
+fn format(s: &str) -> [bool; 6] {
+    [
+        s.contains('('),
+        s.contains(','),
+        s.contains('-'),
+        s.chars().any(|x| x.is_ascii_digit()),
+        s.chars().next().is_some_and(char::is_uppercase),
+        s.split_whitespace().count() > 1,
+    ]
+}
+
+/// Shared words, and shared word endings so forms of the same part of speech match up.
+fn vocabulary(a: &str, b: &str) -> f32 {
+    let words = |s: &str| {
+        normalize(s)
+            .split(' ')
+            .map(str::to_owned)
+            .collect::<HashSet<_>>()
+    };
+    let (a, b) = (words(a), words(b));
+    let shared = a.intersection(&b).count() as f32 / a.union(&b).count().max(1) as f32;
+
+    let endings = |words: &HashSet<String>| {
+        words
+            .iter()
+            .filter_map(|x| {
+                x.char_indices()
+                    .rev()
+                    .nth(1)
+                    .map(|(i, _)| x[i..].to_owned())
+            })
+            .collect::<HashSet<_>>()
+    };
+    let (a, b) = (endings(&a), endings(&b));
+    let endings = a.intersection(&b).count() as f32 / a.union(&b).count().max(1) as f32;
+
+    (shared + endings) / 2.0
+}
@@ -0,0 +1,147 @@
+/// Every `TYPO_RATIO` characters of the answer allows for one typo.
+const TYPO_RATIO: usize = 5;
+
+pub enum Grade {
+    /// Matches one of the accepted answers after normalization.
+    Correct,
+    /// Within a few typos of an accepted answer, still counts as correct.
+    Close(String),
+    /// Not close to any accepted answer, holds the closest one.
+    Wrong(String),
+}
+
+pub enum Diff {
+    Same(char),
+    /// In the answer but not what was typed.
+    Missing(char),
+    /// Typed but not in the answer.
+    Extra(char),
+}
+
+/// Grade a typed answer against a card's answer.
+/// The answer can hold multiple accepted answers split by `delimiter`, empty ones are ignored,
+/// and anything in parentheses, like the `(2)` in `valeo (2)`, can be left out.
+pub fn grade(input: &str, answer: &str, delimiter: &str) -> Grade {
+    let input = normalize(input);
+    let mut closest = (usize::MAX, usize::MAX, answer.to_owned());
+
+    // Splitting on an empty delimiter would accept every single letter of the answer
+    let answers = match delimiter {
+        "" => vec![answer],
+        _ => answer.split(delimiter).collect(),
+    };
+
+    for accepted in answers.into_iter().map(str::trim) {
+        for accepted in [accepted.to_owned(), strip_parentheses(accepted)] {
+            let expected = normalize(&accepted);
+            // Blank answers, like between `;;` or all in parentheses, would accept a blank input
+            if expected.is_empty() {
+                continue;
+            }
+
+            let distance = distance(&input, &expected);
+            if distance == 0 {
+                return Grade::Correct;
+            }
+
+            if distance < closest.0 {
+                closest = (distance, expected.chars().count(), accepted);
+            }
+        }
+    }
+
+    let (distance, len, accepted) = closest;
+    if distance <= len / TYPO_RATIO {
+        Grade::Close(accepted)
+    } else {
+        Grade::Wrong(accepted)
+    }
+}
+
+fn strip_parentheses(s: &str) -> String {
+    let mut depth = 0_usize;
+    let out = s
+        .chars()
+        .filter(|&x| {
+            match x {
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                _ => return depth == 0,
+            }
+            false
+        })
+        .collect::<String>();
+    out.trim().to_owned()
+}
+
+/// Lowercase, remove punctuation and collapse whitespace.
+pub fn normalize(s: &str) -> String {
+    s.to_lowercase()
+        .chars()
+        .map(|x| if x.is_alphanumeric() { x } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Levenshtein distance between two strings.
+fn distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    table(&a, &b)[a.len()][b.len()]
+}
+
+/// Works out which characters of `input` line up with `expected`, ignoring case.
+pub fn diff(input: &str, expected: &str) -> Vec<Diff> {
+    let a = input.chars().collect::<Vec<_>>();
+    let b = expected.chars().collect::<Vec<_>>();
+    let table = table(&a, &b);
+
+    let mut out = Vec::new();
+    let (mut i, mut j) = (a.len(), b.len());
+    while i > 0 || j > 0 {
+        if i > 0 && j > 0 && same(a[i - 1], b[j - 1]) && table[i][j] == table[i - 1][j - 1] {
+            out.push(Diff::Same(b[j - 1]));
+            i -= 1;
+            j -= 1;
+        } else if j > 0 && (i == 0 || table[i][j] == table[i][j - 1] + 1) {
+            out.push(Diff::Missing(b[j - 1]));
+            j -= 1;
+        } else if i > 0 && j > 0 && table[i][j] == table[i - 1][j - 1] + 1 {
+            out.push(Diff::Missing(b[j - 1]));
+            out.push(Diff::Extra(a[i - 1]));
+            i -= 1;
+            j -= 1;
+        } else {
+            out.push(Diff::Extra(a[i - 1]));
+            i -= 1;
+        }
+    }
+
+    out.reverse();
+    out
+}
+
+fn table(a: &[char], b: &[char]) -> Vec<Vec<usize>> {
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in table.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    table[0] = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = !same(a[i - 1], b[j - 1]) as usize;
+            table[i][j] = (table[i - 1][j] + 1)
+                .min(table[i][j - 1] + 1)
+                .min(table[i - 1][j - 1] + cost);
+        }
+    }
+
+    table
+}
+
+fn same(a: char, b: char) -> bool {
+    a.to_lowercase().eq(b.to_lowercase())
+}
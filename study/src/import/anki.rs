@@ -0,0 +1,99 @@
+use std::io::{Cursor, Read, Write};
+
+use rusqlite::Connection;
+use tempfile::NamedTempFile;
+use zip::ZipArchive;
+
+use super::{tags, Import, Importer};
+use crate::card::Card;
+
+/// Newer versions of Anki also include a legacy collection for older clients.
+const COLLECTIONS: [&str; 2] = ["collection.anki21", "collection.anki2"];
+/// The compressed collection from current versions of Anki, which comes with a `collection.anki2` that only has a placeholder note.
+const COMPRESSED_COLLECTION: &str = "collection.anki21b";
+
+/// Anki's `.apkg` deck packages.
+/// These are a zip with the collection as a SQLite database inside.
+/// The first field of each note is the question and the second is the answer, and the note's tags are kept.
+pub struct Anki;
+
+impl Importer for Anki {
+    fn import(&self, raw: &[u8]) -> Import {
+        let mut out = Import::default();
+        match notes(raw) {
+            Ok(notes) => {
+                for (i, (fields, note_tags)) in notes.iter().enumerate() {
+                    let mut fields = fields.split('\x1f').map(strip_html);
+                    match (fields.next(), fields.next()) {
+                        (Some(question), Some(answer)) => out
+                            .cards
+                            .push(Card::new(&question, &answer).with_tags(tags(note_tags))),
+                        _ => out.error(i + 1, "Note has less than two fields"),
+                    }
+                }
+            }
+            Err(e) => out.error(0, e),
+        }
+
+        out
+    }
+}
+
+/// Gets the fields and tags of every note in the package.
+fn notes(raw: &[u8]) -> Result<Vec<(String, String)>, String> {
+    let mut archive = ZipArchive::new(Cursor::new(raw)).map_err(|e| e.to_string())?;
+    let has = |name: &str| archive.file_names().any(|x| x == name);
+    let name = match COLLECTIONS.into_iter().find(|x| has(x)) {
+        Some("collection.anki2") | None if has(COMPRESSED_COLLECTION) => {
+            return Err(
+                "Package uses the newer `collection.anki21b` format, which isn't supported. \
+                 Export it again with \"Support older Anki versions\" checked"
+                    .to_owned(),
+            )
+        }
+        Some(name) => name,
+        None => return Err("No collection found in package".to_owned()),
+    };
+
+    let mut collection = Vec::new();
+    archive
+        .by_name(name)
+        .and_then(|mut x| Ok(x.read_to_end(&mut collection)?))
+        .map_err(|e| e.to_string())?;
+
+    // SQLite needs the database to be in a file.
+    // The temp file has a random name and private permissions, and is deleted on drop even if reading fails.
+    let mut file = NamedTempFile::new().map_err(|e| e.to_string())?;
+    file.write_all(&collection).map_err(|e| e.to_string())?;
+    let connection = Connection::open(file.path()).map_err(|e| e.to_string())?;
+    read_notes(&connection).map_err(|e| e.to_string())
+}
+
+fn read_notes(connection: &Connection) -> rusqlite::Result<Vec<(String, String)>> {
+    connection
+        .prepare("SELECT flds, tags FROM notes ORDER BY id")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect()
+}
+
+/// Anki fields are HTML, so remove the tags and decode the common entities.
+fn strip_html(field: &str) -> String {
+    let mut out = String::new();
+    let mut in_tag = false;
+    for chr in field.chars() {
+        match chr {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => out.push(chr),
+            _ => {}
+        }
+    }
+
+    out.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+        .trim()
+        .to_owned()
+}
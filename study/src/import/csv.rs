@@ -0,0 +1,57 @@
+use ::csv::{Position, ReaderBuilder};
+
+use super::{tags, Import, Importer};
+use crate::card::Card;
+
+/// Comma separated values with the answer in the first column and the question in the second.
+/// An optional third column holds the card's tags.
+/// Fields can be quoted to hold commas or span multiple lines.
+pub struct Csv;
+
+impl Importer for Csv {
+    fn import(&self, raw: &[u8]) -> Import {
+        let mut reader = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(raw);
+        let mut out = Import::default();
+
+        for record in reader.records() {
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => {
+                    let line = e.position().map_or(0, |x| line(raw, x));
+                    out.error(line, e.to_string());
+                    continue;
+                }
+            };
+
+            let line = record.position().map_or(0, |x| line(raw, x));
+            match (record.get(0), record.get(1)) {
+                (Some(answer), Some(question)) => out.cards.push(
+                    Card::new(question, answer).with_tags(tags(record.get(2).unwrap_or_default())),
+                ),
+                _ if record.iter().all(|x| x.trim().is_empty()) => {}
+                _ => out.error(line, "Expected two columns"),
+            }
+        }
+
+        out
+    }
+}
+
+/// The line a record starts on.
+/// The reader's positions are from the end of the last record, before any blank lines it skipped,
+/// so the line is counted from the first byte after them.
+fn line(raw: &[u8], position: &Position) -> usize {
+    let start = (position.byte() as usize).min(raw.len());
+    let skipped = raw[start..]
+        .iter()
+        .take_while(|x| matches!(x, b'\r' | b'\n'))
+        .count();
+    raw[..start + skipped]
+        .iter()
+        .filter(|&&x| x == b'\n')
+        .count()
+        + 1
+}
@@ -0,0 +1,68 @@
+use std::path::Path;
+
+use crate::card::Card;
+
+mod anki;
+mod csv;
+mod quizlet;
+
+pub use self::{anki::Anki, csv::Csv, quizlet::Quizlet};
+
+/// Turns the raw bytes of a file into cards.
+pub trait Importer {
+    fn import(&self, raw: &[u8]) -> Import;
+}
+
+#[derive(Default)]
+pub struct Import {
+    pub cards: Vec<Card>,
+    /// Problems with individual lines, these don't stop the rest of the file from being imported.
+    pub errors: Vec<ImportError>,
+}
+
+pub struct ImportError {
+    /// Zero if the error is with the whole file.
+    pub line: usize,
+    pub message: String,
+}
+
+impl Import {
+    fn error(&mut self, line: usize, message: impl Into<String>) {
+        self.errors.push(ImportError {
+            line,
+            message: message.into(),
+        });
+    }
+}
+
+/// Picks an importer from the file extension.
+/// Anything that isn't a `.csv` or `.apkg` is treated as a Quizlet export with the given separators.
+pub fn importer(
+    path: &Path,
+    term_separator: &str,
+    row_separator: &str,
+    tag_separator: Option<&str>,
+) -> Box<dyn Importer> {
+    match path.extension().and_then(|x| x.to_str()) {
+        Some("csv") => Box::new(Csv),
+        Some("apkg") => Box::new(Anki),
+        _ => Box::new(Quizlet {
+            term_separator: unescape(term_separator),
+            row_separator: unescape(row_separator),
+            tag_separator: tag_separator.map(unescape),
+        }),
+    }
+}
+
+/// Splits a list of tags on whitespace and commas.
+fn tags(s: &str) -> Vec<String> {
+    s.split(|x: char| x.is_whitespace() || x == ',')
+        .filter(|x| !x.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Lets separators like `\t` and `\n` be passed on the command line.
+fn unescape(s: &str) -> String {
+    s.replace("\\t", "\t").replace("\\n", "\n")
+}
@@ -0,0 +1,47 @@
+use super::{tags, Import, Importer};
+use crate::card::Card;
+
+/// Quizlet's export format, a term and definition on each row.
+/// The term is the answer and the definition is the question.
+/// With a tag separator, anything after the last one is taken as the card's tags.
+/// The defaults (tab and newline, no tags) are also the format of the original `data.txt`.
+pub struct Quizlet {
+    pub term_separator: String,
+    pub row_separator: String,
+    pub tag_separator: Option<String>,
+}
+
+impl Importer for Quizlet {
+    fn import(&self, raw: &[u8]) -> Import {
+        let raw = String::from_utf8_lossy(raw);
+        let mut out = Import::default();
+
+        let mut line = 1;
+        for row in raw.split(self.row_separator.as_str()) {
+            let trimmed = row.trim_start_matches(['\r', '\n']);
+            let row_line = line + row[..row.len() - trimmed.len()].matches('\n').count();
+            line += row.matches('\n').count() + self.row_separator.matches('\n').count();
+
+            let row = trimmed.trim_end_matches(['\r', '\n']);
+            if row.trim().is_empty() {
+                continue;
+            }
+
+            let Some((term, definition)) = row.split_once(self.term_separator.as_str()) else {
+                out.error(row_line, format!("Missing separator in `{row}`"));
+                continue;
+            };
+
+            let (definition, card_tags) = match &self.tag_separator {
+                Some(separator) => definition
+                    .rsplit_once(separator.as_str())
+                    .unwrap_or((definition, "")),
+                None => (definition, ""),
+            };
+            out.cards
+                .push(Card::new(definition, term).with_tags(tags(card_tags)));
+        }
+
+        out
+    }
+}
@@ -0,0 +1,14 @@
+//! The flashcard engine behind `study`, without any of the terminal or web front ends.
+//! Load a [`set::Set`], ask for questions and mark answers, everything random comes from an rng you can seed.
+
+pub mod card;
+pub mod distractor;
+pub mod grade;
+pub mod import;
+pub mod progress;
+pub mod quiz;
+pub mod schedule;
+pub mod set;
+
+#[cfg(test)]
+mod test;
@@ -1,100 +1,369 @@
-use std::io::{self, Stdout, Write};
+use std::{
+    fs,
+    io::{self, Stdout, Write},
+    time::Instant,
+};
 
-use card::Card;
+use args::{Args, Command, SetArgs, StatsArgs, StudyArgs, TestArgs};
+use clap::Parser;
 use crossterm::{
     cursor::MoveTo,
     execute, queue,
     style::Print,
-    terminal::{Clear, ClearType, EnterAlternateScreen, SetTitle},
+    terminal::{Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, SetTitle},
 };
 use getch::Getch;
-use set::Set;
-
-mod card;
-mod set;
+use rand::random;
+use study::{
+    import::{self, ImportError},
+    quiz::Test,
+    set::{Answer, LoadError, Mode, Options, Question, Scope, Set},
+};
 
-const CARDS_PER_SECTION: usize = 10;
+mod args;
+#[cfg(test)]
+mod cli_test;
+mod picker;
+mod stats;
+mod web;
+mod write;
 
 fn main() {
-    let mut set = Set::load("data.txt").unwrap();
+    let args = Args::parse();
+    match args.command {
+        Some(Command::Test(args)) => return test(args),
+        Some(Command::Stats(args)) => return stats(args),
+        Some(Command::Serve(args)) => {
+            if let Some(set) = session(&args.set, &args.study) {
+                web::serve(set, &args.host, args.port, &args.study.delimiter);
+            }
+            return;
+        }
+        None => {}
+    }
+
+    let Some(mut set) = session(&args.set, &args.study) else {
+        return;
+    };
+
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, SetTitle("Flashcards")).unwrap();
 
-    let mut waiting = false;
-    let mut card = set.get_current_card().clone();
-    let mut options = set.get_options();
     let getch = Getch::new();
-    update(&set, &card, options.clone(), &mut stdout);
+    let finished = match args.mode.into() {
+        Mode::Choice => choice(&mut set, &getch, &mut stdout),
+        Mode::Write => write::write(&mut set, &args.study.delimiter, &getch, &mut stdout),
+        Mode::Flashcard => flashcard(&mut set, &getch, &mut stdout),
+    };
+
+    if finished {
+        summary(&set, &mut stdout);
+        getch.getch().unwrap();
+    }
+
+    execute!(stdout, LeaveAlternateScreen).unwrap();
+}
+
+/// Loads the set to study, returns `None` if there is nothing to study.
+fn session(set: &SetArgs, args: &StudyArgs) -> Option<Set> {
+    let options = Options {
+        section_size: args.section_size,
+        distractors: args.distractors.into(),
+        seed: args.seed,
+        tags: set.tag.clone(),
+        reverse: set.reverse,
+    };
+    let mut set = load(set, options)?;
+    if args.restart {
+        set.restart();
+    }
+
+    if set.is_finished() {
+        println!("No cards are due, come back later!");
+        return None;
+    }
+
+    Some(set)
+}
+
+/// Loads the set, printing any cards that couldn't be imported.
+/// If the set is a directory the user picks a deck out of it first.
+fn load(args: &SetArgs, options: Options) -> Option<Set> {
+    let path = match args.set.is_dir() {
+        true => picker::pick(&args.set, args, &options, &mut io::stdout())?,
+        false => args.set.clone(),
+    };
+
+    let importer = import::importer(
+        &path,
+        &args.term_separator,
+        &args.row_separator,
+        args.tag_separator.as_deref(),
+    );
+    let (set, errors) = match Set::load(&path, importer.as_ref(), options) {
+        Ok(loaded) => loaded,
+        Err(LoadError::NoCards(errors)) => {
+            print_errors(&errors);
+            eprintln!("No valid cards in {}", path.display());
+            return None;
+        }
+        Err(e) => {
+            eprintln!("Error loading {}: {e}", path.display());
+            return None;
+        }
+    };
+    print_errors(&errors);
+
+    println!("Loaded {} cards", set.cards.len());
+    Some(set)
+}
+
+fn print_errors(errors: &[ImportError]) {
+    for error in errors.iter() {
+        match error.line {
+            0 => eprintln!("Error importing set: {}", error.message),
+            line => eprintln!("Invalid card on line {line}: {}", error.message),
+        }
+    }
+}
+
+/// Writes a practice test and its answer key as both Markdown and HTML.
+fn test(args: TestArgs) {
+    let seed = args.seed.unwrap_or_else(random);
+    let options = Options {
+        section_size: usize::MAX,
+        distractors: Scope::Set,
+        seed: Some(seed),
+        tags: args.set.tag.clone(),
+        reverse: args.set.reverse,
+    };
+    let Some(set) = load(&args.set, options) else {
+        return;
+    };
+
+    let title = &set.name;
+    let cards = set.studied_cards().cloned().collect::<Vec<_>>();
+    let test = Test::generate(title, &cards, args.questions, seed);
+
+    fs::create_dir_all(&args.output).unwrap();
+    for (name, key) in [("test", false), ("key", true)] {
+        let path = |ext| args.output.join(format!("{title}.{name}.{ext}"));
+        fs::write(path("md"), test.markdown(key)).unwrap();
+        fs::write(path("html"), test.html(key)).unwrap();
+    }
+
+    println!(
+        "Wrote a {} question test to {} (seed {seed})",
+        test.questions(),
+        args.output.display()
+    );
+}
+
+/// Prints stats for a set, or every deck in a directory, and exports the review log if asked.
+fn stats(args: StatsArgs) {
+    let paths = match args.set.set.is_dir() {
+        true => picker::deck_paths(&args.set.set),
+        false => vec![args.set.set.clone()],
+    };
+
+    let options = Options {
+        section_size: usize::MAX,
+        distractors: Scope::Set,
+        seed: None,
+        tags: args.set.tag.clone(),
+        reverse: args.set.reverse,
+    };
+    let sets = paths
+        .iter()
+        .filter_map(|path| {
+            let importer = import::importer(
+                path,
+                &args.set.term_separator,
+                &args.set.row_separator,
+                args.set.tag_separator.as_deref(),
+            );
+            match Set::load(path, importer.as_ref(), options.clone()) {
+                Ok((set, _)) => Some(set),
+                Err(e) => {
+                    eprintln!("Error loading {}: {e}", path.display());
+                    None
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    stats::print(&sets, args.hardest);
+    if let Some(path) = args.export {
+        stats::export(&sets, &path).unwrap();
+        println!("\nWrote the review log to {}", path.display());
+    }
+}
+
+/// Runs a multiple choice session.
+/// Returns true if the session was finished, or false if the user quit.
+fn choice(set: &mut Set, getch: &Getch, stdout: &mut Stdout) -> bool {
+    let Some(mut question) = set.get_question() else {
+        return true;
+    };
+    update(set, &question, stdout);
+    let mut shown = Instant::now();
     loop {
         let event = getch.getch().unwrap() as char;
         if event == 'q' {
-            break;
+            set.save().unwrap();
+            return false;
         }
 
-        if waiting {
-            waiting = false;
-            update(&set, &card, options.clone(), &mut stdout);
+        let Some(index) = event.to_digit(10).and_then(|x| x.checked_sub(1)) else {
             continue;
-        }
+        };
 
-        if event.is_numeric() {
-            let index = event.to_digit(10).unwrap() as usize - 1;
-            if index >= options.len() {
-                continue;
-            }
+        let Some(correct_answer) = set.get_current_card().map(|x| x.answer.clone()) else {
+            return true;
+        };
+        let correct = match &question {
+            Question::Choice(options) => match options.get(index as usize) {
+                Some(option) => *option == correct_answer,
+                None => continue,
+            },
+            Question::TrueFalse(shown) if index < 2 => (index == 0) == (*shown == correct_answer),
+            Question::TrueFalse(_) => continue,
+        };
 
-            let correct = set.answer(&options[index]);
-            if !correct {
-                waiting = true;
+        let answer = set.mark(correct, shown.elapsed(), Mode::Choice);
+        set.save().unwrap();
+        match answer {
+            Answer::Correct => {}
+            Answer::Incorrect => {
                 execute!(
                     stdout,
                     Clear(ClearType::All),
                     MoveTo(0, 0),
                     Print(format!(
                         "The correct answer was {}\n[ANY KEY TO CONTINUE]",
-                        card.answer
+                        correct_answer
                     ))
                 )
                 .unwrap();
-                continue;
+                getch.getch().unwrap();
+            }
+            Answer::Finished => return true,
+        }
+
+        let Some(next) = set.get_question() else {
+            return true;
+        };
+        question = next;
+        update(set, &question, stdout);
+        shown = Instant::now();
+    }
+}
+
+/// Runs a session where cards are flipped over and the user says if they knew the answer.
+/// Returns true if the session was finished, or false if the user quit.
+fn flashcard(set: &mut Set, getch: &Getch, stdout: &mut Stdout) -> bool {
+    loop {
+        let Some(answer) = set.get_current_card().map(|x| x.answer.clone()) else {
+            return true;
+        };
+        header(set, "[Q]uit", stdout);
+        queue!(stdout, Print("[ANY KEY TO FLIP]")).unwrap();
+        stdout.flush().unwrap();
+
+        let shown = Instant::now();
+        if getch.getch().unwrap() as char == 'q' {
+            set.save().unwrap();
+            return false;
+        }
+        let latency = shown.elapsed();
+
+        header(set, "[Q]uit", stdout);
+        queue!(
+            stdout,
+            Print(format!("= {answer}\n\n[1] Knew it\n[2] Didn't know it\n"))
+        )
+        .unwrap();
+        stdout.flush().unwrap();
+
+        let correct = loop {
+            match getch.getch().unwrap() as char {
+                '1' => break true,
+                '2' => break false,
+                'q' => {
+                    set.save().unwrap();
+                    return false;
+                }
+                _ => {}
             }
+        };
 
-            card = set.get_current_card().clone();
-            options = set.get_options();
-            update(&set, &card, options.clone(), &mut stdout);
+        let answer = set.mark(correct, latency, Mode::Flashcard);
+        set.save().unwrap();
+        if let Answer::Finished = answer {
+            return true;
         }
     }
 }
 
-fn update(set: &Set, card: &Card, options: Vec<String>, stdout: &mut Stdout) {
+/// Clears the screen and shows the progress through the set and the current question.
+fn header(set: &Set, controls: &str, stdout: &mut Stdout) {
     queue!(stdout, Clear(ClearType::All), MoveTo(0, 0)).unwrap();
+    // There's no question once the session is finished, only the summary
+    let Some(card) = set.get_current_card() else {
+        return;
+    };
 
     queue!(
         stdout,
         Print(format!(
-            "Section {}/{}\tCards {}/{}\t[Q]uit\n ({} left)\n\n",
+            "{}\tSection {}/{}\tMastered {}/{}\t{}\n ({} left)\n\n",
+            set.name,
             set.current_section + 1,
             set.sections.len(),
-            set.current_card + 1,
-            set.cards.len(),
-            CARDS_PER_SECTION - set.current_card,
+            set.mastered(),
+            set.round_size(),
+            controls,
+            set.queue.len(),
         ))
     )
     .unwrap();
 
     queue!(
         stdout,
-        Print(format!(
-            "> {}\n[{}]\n\n",
-            card.question,
-            card.status.to_string()
-        ))
+        Print(format!("> {}\n[{}]\n\n", card.question, card.status))
     )
     .unwrap();
+}
 
-    for (i, option) in options.iter().enumerate() {
-        queue!(stdout, Print(format!("[{}] {}\n", i + 1, option))).unwrap();
+fn update(set: &Set, question: &Question, stdout: &mut Stdout) {
+    header(set, "[Q]uit", stdout);
+
+    match question {
+        Question::Choice(options) => {
+            for (i, option) in options.iter().enumerate() {
+                queue!(stdout, Print(format!("[{}] {}\n", i + 1, option))).unwrap();
+            }
+        }
+        Question::TrueFalse(shown) => {
+            queue!(stdout, Print(format!("{shown}?\n\n[1] True\n[2] False\n"))).unwrap();
+        }
     }
 
     stdout.flush().unwrap();
 }
+
+fn summary(set: &Set, stdout: &mut Stdout) {
+    let summary = set.summary();
+    let accuracy = summary.first_try as f32 / summary.cards.max(1) as f32 * 100.0;
+
+    execute!(
+        stdout,
+        Clear(ClearType::All),
+        MoveTo(0, 0),
+        Print(format!(
+            "Finished!\n\n{} cards in {} answers\n{} right on the first try ({:.0}%)\n\n[ANY KEY TO EXIT]",
+            summary.cards, summary.answers, summary.first_try, accuracy
+        ))
+    )
+    .unwrap();
+}
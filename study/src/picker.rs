@@ -0,0 +1,138 @@
+use std::{
+    fs,
+    io::{Stdout, Write},
+    path::{Path, PathBuf},
+};
+
+use crossterm::{
+    cursor::MoveTo,
+    execute, queue,
+    style::{Print, PrintStyledContent, Stylize},
+    terminal::{Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, SetTitle},
+};
+use getch::Getch;
+
+use study::{
+    import, schedule,
+    set::{Counts, Options, Set},
+};
+
+use crate::args::SetArgs;
+
+/// A deck in the decks directory and how much of it is left to study.
+struct Deck {
+    path: PathBuf,
+    name: String,
+    /// The number of cards, and how many are due and mastered, or why it couldn't be loaded.
+    counts: Result<Counts, String>,
+}
+
+/// Lets the user pick a deck out of a directory.
+/// Returns `None` if there are no decks or the user quit.
+pub fn pick(dir: &Path, args: &SetArgs, options: &Options, stdout: &mut Stdout) -> Option<PathBuf> {
+    let decks = decks(dir, args, options);
+    if decks.is_empty() {
+        println!("No decks found in {}", dir.display());
+        return None;
+    }
+
+    execute!(stdout, EnterAlternateScreen, SetTitle("Flashcards")).unwrap();
+    let getch = Getch::new();
+    let mut selected = 0;
+    let picked = loop {
+        update(dir, &decks, selected, stdout);
+        match getch.getch().unwrap() as char {
+            'j' | 's' => selected = (selected + 1) % decks.len(),
+            'k' | 'w' => selected = (selected + decks.len() - 1) % decks.len(),
+            '\r' | '\n' if decks[selected].counts.is_ok() => break Some(selected),
+            'q' => break None,
+            _ => {}
+        }
+    };
+    execute!(stdout, LeaveAlternateScreen).unwrap();
+
+    picked.map(|x| decks[x].path.clone())
+}
+
+/// Every deck in a directory, sorted by name.
+/// Progress files and hidden files are skipped.
+pub fn deck_paths(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut paths = entries
+        .filter_map(|x| x.ok())
+        .map(|x| x.path())
+        .filter(|x| x.is_file())
+        .filter(|x| {
+            let name = x.file_name().unwrap().to_string_lossy();
+            !name.starts_with('.') && !name.ends_with(".progress.json")
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+}
+
+/// Loads every deck in the directory to count its cards.
+fn decks(dir: &Path, args: &SetArgs, options: &Options) -> Vec<Deck> {
+    let now = schedule::now();
+    deck_paths(dir)
+        .into_iter()
+        .map(|path| {
+            let importer = import::importer(
+                &path,
+                &args.term_separator,
+                &args.row_separator,
+                args.tag_separator.as_deref(),
+            );
+            let counts = Set::load(&path, importer.as_ref(), options.clone())
+                .map(|(set, _)| set.counts(now))
+                .map_err(|e| e.to_string());
+
+            Deck {
+                name: path.file_stem().unwrap().to_string_lossy().into_owned(),
+                path,
+                counts,
+            }
+        })
+        .collect()
+}
+
+fn update(dir: &Path, decks: &[Deck], selected: usize, stdout: &mut Stdout) {
+    queue!(
+        stdout,
+        Clear(ClearType::All),
+        MoveTo(0, 0),
+        Print(format!(
+            "Decks in {}\t[J/K] Move\t[ENTER] Study\t[Q]uit\n\n",
+            dir.display()
+        ))
+    )
+    .unwrap();
+
+    let width = decks
+        .iter()
+        .map(|x| x.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    for (i, deck) in decks.iter().enumerate() {
+        let line = match &deck.counts {
+            Ok(counts) => format!(
+                "{:width$}  {} due\t{}/{} mastered",
+                deck.name, counts.due, counts.mastered, counts.cards
+            ),
+            Err(e) => format!("{:width$}  {e}", deck.name),
+        };
+
+        match (i == selected, deck.counts.is_ok()) {
+            (true, _) => queue!(stdout, PrintStyledContent(format!("> {line}").reverse())),
+            (false, true) => queue!(stdout, Print(format!("  {line}"))),
+            (false, false) => queue!(stdout, PrintStyledContent(format!("  {line}").dark_grey())),
+        }
+        .unwrap();
+        queue!(stdout, Print("\n")).unwrap();
+    }
+
+    stdout.flush().unwrap();
+}
@@ -0,0 +1,90 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    card::{Card, CardStatus, Review},
+    schedule::Schedule,
+};
+
+/// Scheduling state and review history for every card in a set.
+/// Stored in a sidecar file next to the set, so `data.txt` gets `data.progress.json`.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Progress {
+    pub cards: HashMap<String, CardProgress>,
+    /// The unfinished session from the last run, if any.
+    #[serde(default)]
+    pub session: Option<Session>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CardProgress {
+    pub schedule: Schedule,
+    pub reviews: Vec<Review>,
+}
+
+/// Where the user left off in a session.
+/// Cards are stored by key so the session survives reordering the set.
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub sections: Vec<Vec<String>>,
+    pub current_section: usize,
+    pub queue: Vec<String>,
+    pub started: u64,
+    /// The tags the session was filtered to.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The status and streak of every card that has been answered this session.
+    pub statuses: HashMap<String, (CardStatus, u32)>,
+}
+
+impl Progress {
+    /// Load the progress file, or start fresh if it doesn't exist yet.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let raw = match fs::read_to_string(path) {
+            Ok(raw) => raw,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+
+        serde_json::from_str(&raw).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let raw = serde_json::to_string(self)?;
+        fs::write(path, raw)
+    }
+
+    /// Copy the saved schedules and reviews onto the cards.
+    pub fn apply(&self, cards: &mut [Card]) {
+        for card in cards {
+            if let Some(progress) = self.cards.get(&card.key()) {
+                card.schedule = progress.schedule.clone();
+                card.reviews = progress.reviews.clone();
+            }
+        }
+    }
+
+    /// Update the progress with the current state of the cards.
+    /// Progress for cards no longer in the set is kept, in case they get added back.
+    pub fn update(&mut self, cards: &[Card]) {
+        for card in cards.iter().filter(|x| !x.reviews.is_empty()) {
+            self.cards.insert(
+                card.key(),
+                CardProgress {
+                    schedule: card.schedule.clone(),
+                    reviews: card.reviews.clone(),
+                },
+            );
+        }
+    }
+}
+
+/// Gets the path of the progress file for a set.
+pub fn progress_path(set: &Path) -> PathBuf {
+    set.with_extension("progress.json")
+}
@@ -0,0 +1,387 @@
+use std::fmt::Write;
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::{
+    card::Card,
+    distractor::{self, MIN_CHOICES},
+};
+
+/// Matching sections are split into groups of at most this many terms, so they fit on a page.
+const MATCHING_GROUP: usize = 10;
+
+/// A printable practice test.
+pub struct Test {
+    pub title: String,
+    /// The seed the test was made with, printed on the answer key so it can be made again.
+    pub seed: u64,
+    pub sections: Vec<Section>,
+}
+
+pub enum Section {
+    /// Match each question to one of the lettered answers.
+    Matching {
+        questions: Vec<(String, usize)>,
+        answers: Vec<String>,
+    },
+    /// Questions with their options and the index of the right one.
+    Choice(Vec<(String, Vec<String>, usize)>),
+    /// Questions with the answer shown and whether it is the right one.
+    TrueFalse(Vec<(String, String, bool)>),
+    /// Questions to write the answer to, with the answer.
+    Written(Vec<(String, String)>),
+}
+
+impl Test {
+    /// Picks `questions` random cards and splits them between a matching, multiple choice,
+    /// true/false and written section.
+    /// Multiple choice questions without enough different answers are asked as true/false instead.
+    pub fn generate(title: &str, cards: &[Card], questions: usize, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let picked = cards
+            .choose_multiple(&mut rng, questions.min(cards.len()))
+            .collect::<Vec<_>>();
+        let answers = || cards.iter().map(|x| x.answer.as_str());
+
+        // The first few sections take one more question each, so small tests still have every section
+        let size = |i: usize| picked.len() / 4 + usize::from(i < picked.len() % 4);
+        let (matching, rest) = picked.split_at(size(0));
+        let (choice, rest) = rest.split_at(size(1));
+        let (true_false, written) = rest.split_at(size(2));
+
+        let mut sections = Vec::new();
+        for group in matching.chunks(MATCHING_GROUP) {
+            let mut answers = group.iter().map(|x| x.answer.clone()).collect::<Vec<_>>();
+            answers.sort();
+            answers.dedup();
+            answers.shuffle(&mut rng);
+
+            let questions = group
+                .iter()
+                .map(|x| {
+                    let answer = answers.iter().position(|y| *y == x.answer).unwrap();
+                    (x.question.clone(), answer)
+                })
+                .collect();
+            sections.push(Section::Matching { questions, answers });
+        }
+
+        let mut choices = Vec::new();
+        let mut true_false = true_false.to_vec();
+        for card in choice {
+            let options = distractor::options(&card.answer, answers(), &mut rng);
+            if options.len() < MIN_CHOICES {
+                true_false.push(*card);
+                continue;
+            }
+
+            let answer = options.iter().position(|x| *x == card.answer).unwrap();
+            choices.push((card.question.clone(), options, answer));
+        }
+        sections.push(Section::Choice(choices));
+
+        let true_false = true_false
+            .iter()
+            .map(|card| {
+                let candidates = answers().filter(|&x| x != card.answer).collect::<Vec<_>>();
+                let shown = match distractor::pick(&card.answer, &candidates, 1, &mut rng).pop() {
+                    Some(wrong) if rng.gen_bool(0.5) => wrong,
+                    _ => card.answer.clone(),
+                };
+                let correct = shown == card.answer;
+                (card.question.clone(), shown, correct)
+            })
+            .collect();
+        sections.push(Section::TrueFalse(true_false));
+
+        sections.push(Section::Written(
+            written
+                .iter()
+                .map(|x| (x.question.clone(), x.answer.clone()))
+                .collect(),
+        ));
+
+        sections.retain(|x| !x.is_empty());
+        Self {
+            title: title.to_owned(),
+            seed,
+            sections,
+        }
+    }
+
+    /// Total number of questions across every section.
+    pub fn questions(&self) -> usize {
+        self.sections.iter().map(Section::len).sum()
+    }
+
+    pub fn markdown(&self, key: bool) -> String {
+        let mut out = String::new();
+        let mut number = 1;
+
+        if key {
+            writeln!(out, "# {} - Answer Key\n\nSeed: {}", self.title, self.seed).unwrap();
+        } else {
+            writeln!(
+                out,
+                "# {}\n\nName: ____________________ Date: __________",
+                self.title
+            )
+            .unwrap();
+        }
+
+        for section in self.sections.iter() {
+            writeln!(out, "\n## {}\n", section.heading()).unwrap();
+            if !key {
+                writeln!(out, "*{}*\n", section.instructions()).unwrap();
+            }
+
+            match section {
+                Section::Matching { questions, answers } => {
+                    for (question, answer) in questions {
+                        match key {
+                            true => writeln!(
+                                out,
+                                "{number}. **{}** {}",
+                                letter(*answer),
+                                answers[*answer]
+                            ),
+                            false => writeln!(out, "{number}. ____ {question}"),
+                        }
+                        .unwrap();
+                        number += 1;
+                    }
+
+                    if !key {
+                        writeln!(out).unwrap();
+                        for (i, answer) in answers.iter().enumerate() {
+                            writeln!(out, "- **{}.** {answer}", letter(i)).unwrap();
+                        }
+                    }
+                }
+                Section::Choice(questions) => {
+                    for (question, options, answer) in questions {
+                        if key {
+                            writeln!(
+                                out,
+                                "{number}. **{}** {}",
+                                letter(*answer),
+                                options[*answer]
+                            )
+                            .unwrap();
+                        } else {
+                            writeln!(out, "{number}. {question}\n").unwrap();
+                            for (i, option) in options.iter().enumerate() {
+                                writeln!(out, "   - {}. {option}", letter(i)).unwrap();
+                            }
+                            writeln!(out).unwrap();
+                        }
+                        number += 1;
+                    }
+                }
+                Section::TrueFalse(questions) => {
+                    for (question, shown, correct) in questions {
+                        match key {
+                            true => writeln!(
+                                out,
+                                "{number}. **{}**",
+                                if *correct { "True" } else { "False" }
+                            ),
+                            false => writeln!(out, "{number}. ____ {question} - {shown}"),
+                        }
+                        .unwrap();
+                        number += 1;
+                    }
+                }
+                Section::Written(questions) => {
+                    for (question, answer) in questions {
+                        match key {
+                            true => writeln!(out, "{number}. {answer}"),
+                            false => writeln!(
+                                out,
+                                "{number}. {question}\n\n    ______________________________\n"
+                            ),
+                        }
+                        .unwrap();
+                        number += 1;
+                    }
+                }
+            }
+        }
+
+        out
+    }
+
+    pub fn html(&self, key: bool) -> String {
+        let mut out = String::new();
+        let mut number = 1;
+        let title = match key {
+            true => format!("{} - Answer Key", escape(&self.title)),
+            false => escape(&self.title),
+        };
+
+        writeln!(
+            out,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<h1>{title}</h1>"
+        )
+        .unwrap();
+        match key {
+            true => writeln!(out, "<p>Seed: {}</p>", self.seed),
+            false => writeln!(out, "<p>Name: <span class=\"blank\"></span> Date: <span class=\"blank short\"></span></p>"),
+        }
+        .unwrap();
+
+        for section in self.sections.iter() {
+            writeln!(out, "<section>\n<h2>{}</h2>", section.heading()).unwrap();
+            if !key {
+                writeln!(out, "<p><em>{}</em></p>", section.instructions()).unwrap();
+            }
+            writeln!(out, "<ol start=\"{number}\">").unwrap();
+
+            match section {
+                Section::Matching { questions, answers } => {
+                    for (question, answer) in questions {
+                        match key {
+                            true => writeln!(
+                                out,
+                                "<li><b>{}</b> {}</li>",
+                                letter(*answer),
+                                escape(&answers[*answer])
+                            ),
+                            false => writeln!(
+                                out,
+                                "<li><span class=\"blank short\"></span> {}</li>",
+                                escape(question)
+                            ),
+                        }
+                        .unwrap();
+                    }
+                    writeln!(out, "</ol>").unwrap();
+
+                    if !key {
+                        writeln!(out, "<ol class=\"letters\" type=\"A\">").unwrap();
+                        for answer in answers {
+                            writeln!(out, "<li>{}</li>", escape(answer)).unwrap();
+                        }
+                        writeln!(out, "</ol>").unwrap();
+                    }
+                    number += questions.len();
+                }
+                Section::Choice(questions) => {
+                    for (question, options, answer) in questions {
+                        if key {
+                            writeln!(
+                                out,
+                                "<li><b>{}</b> {}</li>",
+                                letter(*answer),
+                                escape(&options[*answer])
+                            )
+                            .unwrap();
+                            continue;
+                        }
+
+                        writeln!(out, "<li>{}\n<ol type=\"A\">", escape(question)).unwrap();
+                        for option in options {
+                            writeln!(out, "<li>{}</li>", escape(option)).unwrap();
+                        }
+                        writeln!(out, "</ol>\n</li>").unwrap();
+                    }
+                    writeln!(out, "</ol>").unwrap();
+                    number += questions.len();
+                }
+                Section::TrueFalse(questions) => {
+                    for (question, shown, correct) in questions {
+                        match key {
+                            true => writeln!(
+                                out,
+                                "<li><b>{}</b></li>",
+                                if *correct { "True" } else { "False" }
+                            ),
+                            false => writeln!(
+                                out,
+                                "<li><span class=\"blank short\"></span> {} &mdash; {}</li>",
+                                escape(question),
+                                escape(shown)
+                            ),
+                        }
+                        .unwrap();
+                    }
+                    writeln!(out, "</ol>").unwrap();
+                    number += questions.len();
+                }
+                Section::Written(questions) => {
+                    for (question, answer) in questions {
+                        match key {
+                            true => writeln!(out, "<li>{}</li>", escape(answer)),
+                            false => writeln!(
+                                out,
+                                "<li>{}<div class=\"line\"></div></li>",
+                                escape(question)
+                            ),
+                        }
+                        .unwrap();
+                    }
+                    writeln!(out, "</ol>").unwrap();
+                    number += questions.len();
+                }
+            }
+
+            writeln!(out, "</section>").unwrap();
+        }
+
+        writeln!(out, "</body>\n</html>").unwrap();
+        out
+    }
+}
+
+impl Section {
+    pub fn heading(&self) -> &'static str {
+        match self {
+            Section::Matching { .. } => "Matching",
+            Section::Choice(_) => "Multiple Choice",
+            Section::TrueFalse(_) => "True or False",
+            Section::Written(_) => "Written",
+        }
+    }
+
+    fn instructions(&self) -> &'static str {
+        match self {
+            Section::Matching { .. } => "Write the letter of the matching answer in the blank.",
+            Section::Choice(_) => "Circle the letter of the correct answer.",
+            Section::TrueFalse(_) => "Write T if the answer is right or F if it is wrong.",
+            Section::Written(_) => "Write out the answer.",
+        }
+    }
+
+    /// Number of questions in the section.
+    pub fn len(&self) -> usize {
+        match self {
+            Section::Matching { questions, .. } => questions.len(),
+            Section::Choice(x) => x.len(),
+            Section::TrueFalse(x) => x.len(),
+            Section::Written(x) => x.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+const STYLE: &str = "body { font-family: serif; max-width: 45em; margin: auto; } \
+section { break-inside: avoid-page; } \
+li { margin: 0.4em 0; } \
+.letters { columns: 2; } \
+.blank { display: inline-block; width: 15em; border-bottom: 1px solid; } \
+.blank.short { width: 3em; } \
+.line { height: 2em; border-bottom: 1px solid; }";
+
+fn letter(index: usize) -> char {
+    (b'A' + index as u8) as char
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,68 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+const DAY: u64 = 60 * 60 * 24;
+/// Cards that won't come up again for this many days count as mastered.
+const MASTERED_INTERVAL: u32 = 21;
+
+/// SM-2 spaced repetition state for a card.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Schedule {
+    pub ease: f32,
+    /// Days between the last review and when the card is due again.
+    pub interval: u32,
+    /// Number of correct reviews in a row.
+    pub repetitions: u32,
+    /// Unix timestamp of when the card should be studied next.
+    pub due: u64,
+}
+
+impl Schedule {
+    pub fn is_due(&self, now: u64) -> bool {
+        self.due <= now
+    }
+
+    pub fn is_mastered(&self) -> bool {
+        self.interval >= MASTERED_INTERVAL
+    }
+
+    /// Update the schedule after a review.
+    /// `quality` is from 0 (complete blackout) to 5 (perfect recall), anything under 3 is a miss.
+    pub fn review(&mut self, quality: u8, now: u64) {
+        if quality >= 3 {
+            self.interval = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (self.interval as f32 * self.ease).round() as u32,
+            };
+            self.repetitions += 1;
+        } else {
+            self.interval = 1;
+            self.repetitions = 0;
+        }
+
+        let miss = 5.0 - quality.min(5) as f32;
+        self.ease = (self.ease + 0.1 - miss * (0.08 + miss * 0.02)).max(1.3);
+        self.due = now + self.interval as u64 * DAY;
+    }
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            ease: 2.5,
+            interval: 0,
+            repetitions: 0,
+            due: 0,
+        }
+    }
+}
+
+/// The current unix timestamp in seconds.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
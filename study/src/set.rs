@@ -1,105 +1,463 @@
-use std::{fs, io, path::Path, str::FromStr};
+use std::{
+    collections::VecDeque,
+    fmt::{self, Display},
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use rand::{seq::SliceRandom, Rng};
+use rand::{rngs::StdRng, seq::SliceRandom, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    card::{Card, CardStatus},
-    CARDS_PER_SECTION,
+    card::{Card, CardStatus, Review},
+    distractor::{self, MIN_CHOICES},
+    import::{Import, ImportError, Importer},
+    progress::{progress_path, Progress, Session},
+    schedule,
 };
 
+/// How many times in a row a card has to be answered correctly in a round to be mastered.
+const MASTERED_STREAK: u32 = 2;
+/// How many cards later a missed card comes back up.
+const REQUEUE_GAP: usize = 3;
+
 pub struct Set {
     pub name: String,
     pub cards: Vec<Card>,
+    progress: Progress,
+    /// Where progress is saved, sets made with [`Set::new`] aren't saved anywhere.
+    progress_path: Option<PathBuf>,
+
+    options: Options,
+    /// Used for shuffling and picking options, pass a seeded one for reproducible sessions.
+    rng: Box<dyn RngCore + Send>,
 
-    pub sections: Vec<Vec<Card>>,
+    /// Indices into `cards` of the cards that are due, split into sections.
+    /// Each section is studied as a learn round.
+    pub sections: Vec<Vec<usize>>,
     pub current_section: usize,
-    pub current_card: usize,
+    /// Cards left in the current round, the front is the current card.
+    pub queue: VecDeque<usize>,
+    /// When the current session was started.
+    started: u64,
+    finished: bool,
+}
+
+/// Settings for how a set is studied.
+#[derive(Clone)]
+pub struct Options {
+    /// Number of cards in each learn round.
+    pub section_size: usize,
+    /// Where wrong answers for multiple choice questions come from.
+    pub distractors: Scope,
+    /// Seed for the random number generator used by [`Set::load`], so sessions can be reproduced.
+    pub seed: Option<u64>,
+    /// Only study cards with one of these tags, or every card if empty.
+    pub tags: Vec<String>,
+    /// Swap the question and answer of every card.
+    pub reverse: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// Pick the answer out of four options.
+    Choice,
+    /// Type out the answer.
+    Write,
+    /// Flip the card over and say if you knew it.
+    Flashcard,
+}
+
+#[derive(Clone, Copy)]
+pub enum Scope {
+    /// Any card in the set.
+    Set,
+    /// Only cards in the current learn round.
+    Section,
+    /// Only cards that share a tag with the current card.
+    Tag,
+}
+
+pub enum Question {
+    /// Pick the answer out of a list of options.
+    Choice(Vec<String>),
+    /// Is this the answer to the card?
+    TrueFalse(String),
+}
+
+pub enum Answer {
+    Correct,
+    Incorrect,
+    /// The last card of the last round was mastered, or there were no cards left to mark.
+    Finished,
+}
+
+/// Why a set couldn't be loaded.
+pub enum LoadError {
+    /// The set or its progress file couldn't be read.
+    Io(io::Error),
+    /// None of the cards could be imported, with what was wrong with each line.
+    NoCards(Vec<ImportError>),
+}
+
+pub struct Summary {
+    pub cards: usize,
+    /// Cards that were answered correctly the first time they came up this session.
+    pub first_try: usize,
+    pub answers: usize,
+}
+
+/// How much of the cards being studied is left, as shown by the deck picker and stats.
+pub struct Counts {
+    pub cards: usize,
+    pub due: usize,
+    pub mastered: usize,
 }
 
 impl Set {
-    pub fn load(file: impl AsRef<Path>) -> io::Result<Self> {
-        let raw = fs::read_to_string(file)?;
-        let mut cards = Vec::new();
-        let mut success = true;
-
-        for line in raw.lines() {
-            let card = match Card::from_str(line) {
-                Ok(card) => card,
-                Err(_) => {
-                    eprintln!("Invalid card: {}", line);
-                    success = false;
-                    continue;
-                }
-            };
-            cards.push(card);
-        }
-
-        if !success {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Invalid card(s)",
-            ));
-        }
-        println!("Loaded {} cards", cards.len());
-
-        let mut sections = Vec::new();
-        for i in 0..(cards.len() / CARDS_PER_SECTION) {
-            let start = i * CARDS_PER_SECTION;
-            let end = start + CARDS_PER_SECTION;
-            sections.push(cards[start..end].to_vec());
-        }
-
-        Ok(Self {
-            name: "Default".to_string(),
+    /// Make a set out of some cards, without loading or saving any progress.
+    /// All of the randomness comes from `rng`.
+    pub fn new(
+        name: impl Into<String>,
+        mut cards: Vec<Card>,
+        options: Options,
+        rng: impl RngCore + Send + 'static,
+    ) -> Self {
+        if options.reverse {
+            cards.iter_mut().for_each(Card::reverse);
+        }
+
+        let mut set = Self {
+            name: name.into(),
             cards,
-            sections,
+            progress: Progress::default(),
+            progress_path: None,
+            rng: Box::new(rng),
+            options,
+            sections: Vec::new(),
             current_section: 0,
-            current_card: 0,
-        })
+            queue: VecDeque::new(),
+            started: schedule::now(),
+            finished: false,
+        };
+        set.restart();
+        set
     }
 
-    pub fn get_current_card(&self) -> &Card {
-        &self.sections[self.current_section][self.current_card]
+    /// Load a set with the given importer.
+    /// Lines that fail to import are skipped and returned along with the set.
+    pub fn load(
+        file: impl AsRef<Path>,
+        importer: &dyn Importer,
+        options: Options,
+    ) -> Result<(Self, Vec<ImportError>), LoadError> {
+        let file = file.as_ref();
+        let raw = fs::read(file)?;
+        let Import { cards, errors } = importer.import(&raw);
+
+        if cards.is_empty() {
+            return Err(LoadError::NoCards(errors));
+        }
+
+        let name = file
+            .file_stem()
+            .map(|x| x.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let rng = match options.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let mut set = Self::new(name, cards, options, rng);
+
+        let progress_path = progress_path(file);
+        set.progress = Progress::load(&progress_path)?;
+        set.progress_path = Some(progress_path);
+        set.progress.apply(&mut set.cards);
+
+        if !set.resume() {
+            set.restart();
+        }
+
+        Ok((set, errors))
+    }
+
+    /// Start a new session with the cards that are currently due.
+    /// Any saved position and card statuses are thrown away.
+    /// If no cards are due the session is finished straight away.
+    pub fn restart(&mut self) {
+        let now = schedule::now();
+        let due = (0..self.cards.len())
+            .filter(|&i| self.cards[i].schedule.is_due(now))
+            .filter(|&i| self.cards[i].has_tag(&self.options.tags))
+            .collect::<Vec<_>>();
+
+        self.sections = due
+            .chunks(self.options.section_size.max(1))
+            .map(|x| x.to_vec())
+            .collect();
+
+        for card in self.cards.iter_mut() {
+            card.status = CardStatus::New;
+            card.streak = 0;
+        }
+
+        self.current_section = 0;
+        self.start_round();
+        self.started = now;
+        self.finished = self.queue.is_empty();
     }
 
-    // Pick four random cards from the current section, making sure not to duplicate any answers
-    pub fn get_options(&self) -> Vec<String> {
-        let mut options = Vec::new();
-        let current_card = self.get_current_card();
+    /// Pick up the session saved in the progress file, if there is one.
+    /// Returns false if there was no session or the set changed so it no longer lines up.
+    fn resume(&mut self) -> bool {
+        let Some(session) = self.progress.session.take() else {
+            return false;
+        };
+
+        if session.tags != self.options.tags {
+            return false;
+        }
+
+        let index = |key: &String| self.cards.iter().position(|x| &x.key() == key);
+        let Some(sections) = session
+            .sections
+            .iter()
+            .map(|x| x.iter().map(index).collect::<Option<Vec<_>>>())
+            .collect::<Option<Vec<_>>>()
+        else {
+            return false;
+        };
+        let Some(queue) = session
+            .queue
+            .iter()
+            .map(index)
+            .collect::<Option<VecDeque<_>>>()
+        else {
+            return false;
+        };
 
-        let mut rng = rand::thread_rng();
-        while options.len() < 4 {
-            let card = self.cards.choose(&mut rng).unwrap();
-            if !options.contains(&card.answer) {
-                options.push(card.answer.clone());
+        if session.current_section >= sections.len() || queue.is_empty() {
+            return false;
+        }
+
+        for card in self.cards.iter_mut() {
+            if let Some((status, streak)) = session.statuses.get(&card.key()) {
+                card.status = status.clone();
+                card.streak = *streak;
             }
         }
 
-        if !options.contains(&current_card.answer) {
-            options[rng.gen_range(0..4)] = current_card.answer.clone();
+        self.sections = sections;
+        self.current_section = session.current_section;
+        self.queue = queue;
+        self.started = session.started;
+        true
+    }
+
+    fn session(&self) -> Session {
+        let sections = self
+            .sections
+            .iter()
+            .map(|x| x.iter().map(|&i| self.cards[i].key()).collect())
+            .collect();
+        let statuses = self
+            .cards
+            .iter()
+            .filter(|x| x.status != CardStatus::New)
+            .map(|x| (x.key(), (x.status.clone(), x.streak)))
+            .collect();
+
+        Session {
+            sections,
+            current_section: self.current_section,
+            queue: self.queue.iter().map(|&i| self.cards[i].key()).collect(),
+            started: self.started,
+            tags: self.options.tags.clone(),
+            statuses,
         }
+    }
+
+    /// Stats for the cards in this session.
+    pub fn summary(&self) -> Summary {
+        let mut summary = Summary {
+            cards: 0,
+            first_try: 0,
+            answers: 0,
+        };
+
+        for card in self.sections.iter().flatten().map(|&i| &self.cards[i]) {
+            let reviews = card
+                .reviews
+                .iter()
+                .filter(|x| x.time >= self.started)
+                .collect::<Vec<_>>();
+            summary.cards += 1;
+            summary.first_try += reviews.first().is_some_and(|x| x.correct) as usize;
+            summary.answers += reviews.len();
+        }
+
+        summary
+    }
+
+    /// Write the schedules and review history of every card to the progress file.
+    /// Unless the session is finished, the current position is saved too.
+    pub fn save(&mut self) -> io::Result<()> {
+        self.progress.update(&self.cards);
+        self.progress.session = (!self.finished).then(|| self.session());
+        match &self.progress_path {
+            Some(path) => self.progress.save(path),
+            None => Ok(()),
+        }
+    }
+
+    /// True once the last card of the last round has been mastered, or if no cards were due.
+    /// There's a current card whenever this is false.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
 
-        options
+    /// The cards this set is filtered down to with `Options::tags`.
+    pub fn studied_cards(&self) -> impl Iterator<Item = &Card> {
+        self.cards.iter().filter(|x| x.has_tag(&self.options.tags))
     }
 
-    pub fn answer(&mut self, ans: &str) -> bool {
-        let mut current_card = &mut self.sections[self.current_section][self.current_card];
+    /// Counts the studied cards, and how many of them are due at `now` and mastered.
+    pub fn counts(&self, now: u64) -> Counts {
+        let mut counts = Counts {
+            cards: 0,
+            due: 0,
+            mastered: 0,
+        };
 
-        if ans == current_card.answer {
-            current_card.status = CardStatus::Mastered;
-            self.current_card += 1;
-            if self.current_card >= CARDS_PER_SECTION {
-                self.current_card = 0;
-                self.current_section += 1;
-                if self.current_section >= self.sections.len() {
-                    println!("Finished!");
-                    std::process::exit(0);
-                }
+        for card in self.studied_cards() {
+            counts.cards += 1;
+            counts.due += card.schedule.is_due(now) as usize;
+            counts.mastered += card.schedule.is_mastered() as usize;
+        }
+
+        counts
+    }
+
+    /// The card being asked, or `None` once the session is finished.
+    pub fn get_current_card(&self) -> Option<&Card> {
+        self.queue.front().map(|&i| &self.cards[i])
+    }
+
+    /// Number of cards in the current round.
+    pub fn round_size(&self) -> usize {
+        self.sections.get(self.current_section).map_or(0, Vec::len)
+    }
+
+    /// Number of cards in the current round that have been mastered.
+    pub fn mastered(&self) -> usize {
+        self.sections
+            .get(self.current_section)
+            .into_iter()
+            .flatten()
+            .filter(|&&i| self.cards[i].status == CardStatus::Mastered)
+            .count()
+    }
+
+    fn start_round(&mut self) {
+        self.queue = self
+            .sections
+            .get(self.current_section)
+            .map(|x| x.iter().copied().collect())
+            .unwrap_or_default();
+    }
+
+    /// Options for the current card, with the wrong ones picked from the set or current round.
+    pub fn get_options(&mut self) -> Option<Vec<String>> {
+        let card = self.get_current_card()?;
+        let answer = card.answer.clone();
+        let pool: Vec<usize> = match self.options.distractors {
+            Scope::Set => (0..self.cards.len()).collect(),
+            Scope::Section => self.sections[self.current_section].clone(),
+            Scope::Tag if card.tags.is_empty() => (0..self.cards.len()).collect(),
+            Scope::Tag => (0..self.cards.len())
+                .filter(|&i| self.cards[i].has_tag(&card.tags))
+                .collect(),
+        };
+
+        Some(distractor::options(
+            &answer,
+            pool.iter().map(|&i| self.cards[i].answer.as_str()),
+            &mut self.rng,
+        ))
+    }
+
+    /// Makes a multiple choice question for the current card,
+    /// or a true/false one if there aren't enough different answers to choose from.
+    pub fn get_question(&mut self) -> Option<Question> {
+        let options = self.get_options()?;
+        if options.len() >= MIN_CHOICES {
+            return Some(Question::Choice(options));
+        }
+
+        // The options always include the card's own answer
+        let shown = options.choose(&mut self.rng)?.to_owned();
+        Some(Question::TrueFalse(shown))
+    }
+
+    /// Record the current card as answered correctly or not, and how long it took.
+    /// Cards that were missed come back up a few cards later and cards that haven't been
+    /// answered correctly enough times in a row yet go to the back of the round.
+    /// Once the session is finished there's nothing to mark, so nothing is recorded.
+    pub fn mark(&mut self, correct: bool, latency: Duration, mode: Mode) -> Answer {
+        let Some(index) = self.queue.pop_front() else {
+            self.finished = true;
+            return Answer::Finished;
+        };
+        let card = &mut self.cards[index];
+        card.review(Review {
+            time: schedule::now(),
+            correct,
+            latency: Some(latency.as_millis() as u64),
+            mode: Some(mode),
+        });
+
+        if !correct {
+            card.status = CardStatus::Learning;
+            card.streak = 0;
+            self.queue.insert(REQUEUE_GAP.min(self.queue.len()), index);
+            return Answer::Incorrect;
+        }
+
+        card.streak += 1;
+        if card.streak >= MASTERED_STREAK {
+            card.status = CardStatus::Mastered;
+        } else {
+            card.status = CardStatus::Learning;
+            self.queue.push_back(index);
+        }
+
+        if self.queue.is_empty() {
+            if self.current_section + 1 >= self.sections.len() {
+                self.finished = true;
+                return Answer::Finished;
             }
-            return true;
+
+            self.current_section += 1;
+            self.start_round();
         }
 
-        current_card.status = CardStatus::Learning;
-        false
+        Answer::Correct
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => e.fmt(f),
+            Self::NoCards(_) => f.write_str("No valid cards"),
+        }
     }
 }
@@ -0,0 +1,167 @@
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use chrono::{Local, NaiveDate, TimeZone};
+use serde::Serialize;
+
+use study::{
+    card::{Card, Review},
+    schedule,
+    set::{Mode, Set},
+};
+
+/// How many of the most recent days with reviews to show accuracy for.
+const DAYS: usize = 14;
+
+/// One row of the exported review log.
+#[derive(Serialize)]
+struct LogEntry<'a> {
+    deck: &'a str,
+    question: &'a str,
+    answer: &'a str,
+    tags: String,
+    time: u64,
+    correct: bool,
+    latency_ms: Option<u64>,
+    mode: Option<Mode>,
+}
+
+/// Prints how much of each deck is mastered, the cards missed the most and accuracy by day.
+pub fn print(sets: &[Set], hardest: usize) {
+    let now = schedule::now();
+
+    println!("Decks");
+    let width = sets
+        .iter()
+        .map(|x| x.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    for set in sets {
+        let counts = set.counts(now);
+        let cards = set.studied_cards().collect::<Vec<_>>();
+        let reviews = cards.iter().flat_map(|x| x.reviews.iter());
+        println!(
+            "  {:width$}  {}/{} mastered ({:.0}%)\t{} due\t{} accuracy",
+            set.name,
+            counts.mastered,
+            counts.cards,
+            counts.mastered as f32 / counts.cards.max(1) as f32 * 100.0,
+            counts.due,
+            accuracy(reviews),
+        );
+    }
+
+    println!("\nHardest cards");
+    let mut missed = sets
+        .iter()
+        .flat_map(|set| set.studied_cards().map(move |card| (set, card)))
+        .filter(|(_, card)| card.reviews.iter().any(|x| !x.correct))
+        .collect::<Vec<_>>();
+    missed.sort_by(|(_, a), (_, b)| {
+        let rate = |x: &Card| correct(x.reviews.iter()) as f32 / x.reviews.len() as f32;
+        rate(a)
+            .total_cmp(&rate(b))
+            .then(b.reviews.len().cmp(&a.reviews.len()))
+    });
+
+    if missed.is_empty() {
+        println!("  No missed cards yet");
+    }
+    for (set, card) in missed.iter().take(hardest) {
+        println!(
+            "  {} of {}\t{}\t{}\t{} -> {}",
+            accuracy(card.reviews.iter()),
+            card.reviews.len(),
+            latency(card.reviews.iter()),
+            set.name,
+            card.question,
+            card.answer,
+        );
+    }
+
+    println!("\nAccuracy by day");
+    let mut days = BTreeMap::<NaiveDate, Vec<&Review>>::new();
+    for review in sets
+        .iter()
+        .flat_map(|x| x.studied_cards())
+        .flat_map(|x| x.reviews.iter())
+    {
+        days.entry(date(review.time)).or_default().push(review);
+    }
+
+    if days.is_empty() {
+        println!("  No reviews yet");
+    }
+    let skip = days.len().saturating_sub(DAYS);
+    for (day, reviews) in days.iter().skip(skip) {
+        println!(
+            "  {day}  {} accuracy\t{} answers\t{}",
+            accuracy(reviews.iter().copied()),
+            reviews.len(),
+            latency(reviews.iter().copied()),
+        );
+    }
+}
+
+/// Writes every review of every card to a file, as JSON if it ends in `.json` or CSV otherwise.
+pub fn export(sets: &[Set], path: &Path) -> io::Result<()> {
+    let mut log = Vec::new();
+    for set in sets {
+        for card in set.studied_cards() {
+            for review in card.reviews.iter() {
+                log.push(LogEntry {
+                    deck: &set.name,
+                    question: &card.question,
+                    answer: &card.answer,
+                    tags: card.tags.join(" "),
+                    time: review.time,
+                    correct: review.correct,
+                    latency_ms: review.latency,
+                    mode: review.mode,
+                });
+            }
+        }
+    }
+    log.sort_by_key(|x| x.time);
+
+    if path.extension().is_some_and(|x| x == "json") {
+        return fs::write(path, serde_json::to_string_pretty(&log)?);
+    }
+
+    let mut writer = csv::Writer::from_path(path)?;
+    for entry in log {
+        writer.serialize(entry)?;
+    }
+    writer.flush()
+}
+
+fn correct<'a>(reviews: impl Iterator<Item = &'a Review>) -> usize {
+    reviews.filter(|x| x.correct).count()
+}
+
+fn accuracy<'a>(reviews: impl Iterator<Item = &'a Review> + Clone) -> String {
+    let total = reviews.clone().count();
+    match total {
+        0 => "-".to_owned(),
+        _ => format!("{:.0}%", correct(reviews) as f32 / total as f32 * 100.0),
+    }
+}
+
+/// The average time taken to answer, for the reviews that have it recorded.
+fn latency<'a>(reviews: impl Iterator<Item = &'a Review>) -> String {
+    let latencies = reviews.filter_map(|x| x.latency).collect::<Vec<_>>();
+    match latencies.len() {
+        0 => "-".to_owned(),
+        len => format!(
+            "{:.1}s",
+            latencies.iter().sum::<u64>() as f32 / len as f32 / 1000.0
+        ),
+    }
+}
+
+fn date(time: u64) -> NaiveDate {
+    Local
+        .timestamp_opt(time as i64, 0)
+        .single()
+        .unwrap_or_default()
+        .date_naive()
+}
@@ -0,0 +1,506 @@
+use std::{
+    fs,
+    io::{Cursor, Write},
+    time::Duration,
+};
+
+use rand::{rngs::StdRng, SeedableRng};
+use rusqlite::Connection;
+use zip::{write::FileOptions, ZipWriter};
+
+use crate::{
+    card::{Card, CardStatus},
+    distractor,
+    grade::{self, Diff, Grade},
+    import::{Anki, Csv, Importer, Quizlet},
+    quiz::{Section, Test},
+    schedule::{self, Schedule},
+    set::{Answer, Mode, Options, Question, Scope, Set},
+};
+
+fn set(answers: &[&str], section_size: usize) -> Set {
+    let cards = answers
+        .iter()
+        .enumerate()
+        .map(|(i, x)| Card::new(&format!("Question {i}"), x))
+        .collect();
+    let options = Options {
+        section_size,
+        distractors: Scope::Set,
+        seed: None,
+        tags: Vec::new(),
+        reverse: false,
+    };
+    Set::new("test", cards, options, StdRng::seed_from_u64(0))
+}
+
+fn mark(set: &mut Set, correct: bool) -> Answer {
+    set.mark(correct, Duration::from_millis(500), Mode::Choice)
+}
+
+fn queue(set: &Set) -> Vec<usize> {
+    set.queue.iter().copied().collect()
+}
+
+fn grade(input: &str, answer: &str) -> String {
+    match grade::grade(input, answer, ",") {
+        Grade::Correct => "correct".to_owned(),
+        Grade::Close(expected) => format!("close {expected}"),
+        Grade::Wrong(expected) => format!("wrong {expected}"),
+    }
+}
+
+#[test]
+fn test_nothing_due() {
+    let mut set = set(&["a", "b"], 10);
+    for card in set.cards.iter_mut() {
+        card.schedule.review(5, schedule::now());
+    }
+
+    // Restarting with nothing due finishes the session instead of leaving it without a card
+    set.restart();
+    assert!(set.is_finished());
+    assert!(set.get_current_card().is_none());
+    assert!(set.get_question().is_none());
+    assert_eq!((set.mastered(), set.round_size()), (0, 0));
+    assert!(matches!(mark(&mut set, true), Answer::Finished));
+    assert!(set.cards.iter().all(|x| x.reviews.is_empty()));
+
+    // Marking after the last card was mastered doesn't record anything either
+    let mut set = self::set(&["a"], 10);
+    mark(&mut set, true);
+    assert!(matches!(mark(&mut set, true), Answer::Finished));
+    assert!(set.get_current_card().is_none());
+    assert!(matches!(mark(&mut set, true), Answer::Finished));
+    assert_eq!(set.cards[0].reviews.len(), 2);
+}
+
+#[test]
+fn test_schedule() {
+    const DAY: u64 = 60 * 60 * 24;
+    let mut schedule = Schedule::default();
+    assert!(schedule.is_due(0));
+
+    let intervals = (0..4)
+        .map(|_| {
+            schedule.review(4, 0);
+            schedule.interval
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(intervals, [1, 6, 15, 38]);
+    assert_eq!(schedule.repetitions, 4);
+    assert!((schedule.ease - 2.5).abs() < 1e-5);
+    assert_eq!(schedule.due, 38 * DAY);
+    assert!(!schedule.is_due(DAY));
+    assert!(schedule.is_mastered());
+
+    // A miss starts over and makes the card harder
+    schedule.review(1, 100);
+    assert_eq!((schedule.interval, schedule.repetitions), (1, 0));
+    assert_eq!(schedule.due, 100 + DAY);
+    assert!((schedule.ease - 1.96).abs() < 1e-5);
+    assert!(!schedule.is_mastered());
+
+    for _ in 0..5 {
+        schedule.review(0, 0);
+    }
+    assert_eq!(schedule.ease, 1.3);
+}
+
+#[test]
+fn test_resume() {
+    let dir = std::env::temp_dir().join(format!("study_resume_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("set.csv");
+    fs::write(&file, "a,1\nb,2\nc,3\nd,4\ne,5\n").unwrap();
+
+    let options = Options {
+        section_size: 2,
+        distractors: Scope::Set,
+        seed: Some(0),
+        tags: Vec::new(),
+        reverse: false,
+    };
+    let load = || Set::load(&file, &Csv, options.clone()).ok().unwrap().0;
+    let keys = |set: &Set| {
+        let queue = set.queue.iter().map(|&i| set.cards[i].key());
+        queue.collect::<Vec<_>>()
+    };
+
+    // Master the first round and miss a card in the second
+    let mut set = load();
+    while set.current_section == 0 {
+        mark(&mut set, true);
+    }
+    mark(&mut set, false);
+    let queue = keys(&set);
+    let statuses = set
+        .cards
+        .iter()
+        .map(|x| (x.status.clone(), x.streak))
+        .collect::<Vec<_>>();
+    set.save().unwrap();
+
+    // Loading again picks up where the session left off
+    let mut set = load();
+    assert_eq!(set.current_section, 1);
+    assert_eq!(keys(&set), queue);
+    let resumed = set.cards.iter().map(|x| (x.status.clone(), x.streak));
+    assert!(resumed.eq(statuses));
+    assert_eq!(set.cards.iter().map(|x| x.reviews.len()).sum::<usize>(), 5);
+
+    // Restarting throws the session away and only keeps the cards that haven't been reviewed yet
+    set.restart();
+    assert_eq!(set.current_section, 0);
+    assert!(set
+        .cards
+        .iter()
+        .all(|x| x.status == CardStatus::New && x.streak == 0));
+    let due = set.sections.iter().flatten().map(|&i| set.cards[i].key());
+    assert!(due.eq(set
+        .cards
+        .iter()
+        .filter(|x| x.reviews.is_empty())
+        .map(Card::key)));
+
+    // A finished session isn't resumed
+    while !set.is_finished() {
+        mark(&mut set, true);
+    }
+    set.save().unwrap();
+    assert!(load().is_finished());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_grade() {
+    assert_eq!(grade("Valeo!", "valeo"), "correct");
+    // Parentheses can be left out, or typed
+    assert_eq!(grade("valeo", "valeo (2)"), "correct");
+    assert_eq!(grade("valeo 2", "valeo (2)"), "correct");
+    // Any of the answers split by the delimiter is accepted
+    assert_eq!(grade("to bear", "to carry, to bear"), "correct");
+    assert_eq!(grade("to cary", "to carry, to bear"), "close to carry");
+
+    // One typo for every five characters
+    assert_eq!(grade("amicos", "amicus"), "close amicus");
+    assert_eq!(grade("amxcos", "amicus"), "wrong amicus");
+    assert_eq!(grade("agar", "ager"), "wrong ager");
+    assert_eq!(grade("valo", "valeo (2)"), "close valeo");
+
+    // Blank answers don't accept a blank input, and an empty delimiter doesn't split the answer
+    assert_eq!(grade("", "to carry,, to bear"), "wrong to bear");
+    assert_eq!(grade("", "(2)"), "wrong (2)");
+    assert!(matches!(grade::grade("a", "amicus", ""), Grade::Wrong(_)));
+    assert!(matches!(
+        grade::grade("amicus", "amicus", ""),
+        Grade::Correct
+    ));
+}
+
+#[test]
+fn test_diff() {
+    let diff = |input, expected| {
+        grade::diff(input, expected)
+            .into_iter()
+            .map(|x| match x {
+                Diff::Same(chr) => chr.to_string(),
+                Diff::Missing(chr) => format!("+{chr}"),
+                Diff::Extra(chr) => format!("-{chr}"),
+            })
+            .collect::<String>()
+    };
+
+    assert_eq!(diff("helo", "Hello"), "He+llo");
+    assert_eq!(diff("cat", "cot"), "c-a+ot");
+    assert_eq!(diff("boats", "boat"), "boat-s");
+    assert_eq!(diff("", "ab"), "+a+b");
+}
+
+#[test]
+fn test_import_lines() {
+    let lines = |importer: &dyn Importer, raw: &str| {
+        let import = importer.import(raw.as_bytes());
+        let errors = import.errors.iter().map(|x| x.line).collect::<Vec<_>>();
+        (import.cards, errors)
+    };
+
+    let (cards, errors) = lines(&Csv, "a,one\nonly\n\"multi\nline\",two,verb\n\nbad\n");
+    assert_eq!(errors, [2, 6]);
+    assert_eq!(cards.len(), 2);
+    assert_eq!(cards[1].answer, "multi\nline");
+    assert_eq!(cards[1].tags, ["verb"]);
+
+    let quizlet = |row: &str, tag: Option<&str>| Quizlet {
+        term_separator: "\t".to_owned(),
+        row_separator: row.to_owned(),
+        tag_separator: tag.map(str::to_owned),
+    };
+
+    let (cards, errors) = lines(
+        &quizlet("\n", None),
+        "a\tone\r\n\r\nno separator\nc\tthree\twith a tab\n",
+    );
+    assert_eq!(errors, [3]);
+    assert_eq!(cards.len(), 2);
+    assert_eq!(cards[0].question, "one");
+    assert_eq!(cards[1].question, "three\twith a tab");
+    assert!(cards[1].tags.is_empty());
+
+    let (cards, errors) = lines(
+        &quizlet(";;", Some("|")),
+        "a\tone|verb, noun;;\nb two;;\n\nc\tx|y|z",
+    );
+    assert_eq!(errors, [2]);
+    assert_eq!(cards[0].tags, ["verb", "noun"]);
+    assert_eq!(cards[1].question, "x|y");
+    assert_eq!(cards[1].tags, ["z"]);
+}
+
+#[test]
+fn test_import_anki() {
+    let dir = std::env::temp_dir().join(format!("study_anki_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("collection.anki2");
+    let connection = Connection::open(&path).unwrap();
+    connection
+        .execute(
+            "CREATE TABLE notes (id INTEGER PRIMARY KEY, flds TEXT, tags TEXT)",
+            [],
+        )
+        .unwrap();
+    for note in [
+        (2, "amicus\x1f<b>friend</b>&nbsp;&amp; ally", " noun latin "),
+        (1, "valeo<br>", ""),
+        (3, "<div>ager</div>\x1ffield\x1fextra", "noun"),
+    ] {
+        connection
+            .execute("INSERT INTO notes VALUES (?1, ?2, ?3)", note)
+            .unwrap();
+    }
+    drop(connection);
+    let collection = fs::read(&path).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let package = |files: &[(&str, &[u8])]| {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, raw) in files {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(raw).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    };
+    let import = |raw: &[u8]| {
+        let import = Anki.import(raw);
+        let errors = import.errors.iter().map(|x| x.line).collect::<Vec<_>>();
+        (import.cards, errors)
+    };
+
+    // Notes come out in id order, with the HTML stripped
+    let (cards, errors) = import(&package(&[("collection.anki2", &collection)]));
+    assert_eq!(errors, [1]);
+    assert_eq!(cards.len(), 2);
+    assert_eq!(
+        (cards[0].question.as_str(), cards[0].answer.as_str()),
+        ("amicus", "friend & ally")
+    );
+    assert_eq!(cards[0].tags, ["noun", "latin"]);
+    assert_eq!(
+        (cards[1].question.as_str(), cards[1].answer.as_str()),
+        ("ager", "field")
+    );
+
+    // The newer collection is preferred over the legacy one
+    let (cards, _) = import(&package(&[
+        ("collection.anki2", b"not a database"),
+        ("collection.anki21", &collection),
+    ]));
+    assert_eq!(cards.len(), 2);
+
+    // Problems with the whole package are on line zero
+    for files in [
+        &[
+            ("collection.anki2", &b"placeholder"[..]),
+            ("collection.anki21b", b""),
+        ][..],
+        &[("media", b"{}")],
+    ] {
+        let (cards, errors) = import(&package(files));
+        assert!(cards.is_empty());
+        assert_eq!(errors, [0]);
+    }
+    assert_eq!(import(b"not a zip").1, [0]);
+}
+
+#[test]
+fn test_learn_rounds() {
+    let mut set = set(&["a", "b", "c", "d", "e", "f"], 4);
+    assert_eq!(set.sections, [vec![0, 1, 2, 3], vec![4, 5]]);
+    assert_eq!(queue(&set), [0, 1, 2, 3]);
+
+    // Missed cards come back three cards later
+    assert!(matches!(mark(&mut set, false), Answer::Incorrect));
+    assert_eq!(queue(&set), [1, 2, 3, 0]);
+    assert!(set.cards[0].status == CardStatus::Learning);
+
+    // Right once goes to the back, right twice in a row is mastered
+    for _ in 0..4 {
+        assert!(matches!(mark(&mut set, true), Answer::Correct));
+    }
+    assert_eq!(queue(&set), [1, 2, 3, 0]);
+    assert_eq!(set.mastered(), 0);
+
+    for _ in 0..3 {
+        mark(&mut set, true);
+    }
+    assert_eq!(queue(&set), [0]);
+    assert_eq!(set.mastered(), 3);
+    assert!(set.cards[1].status == CardStatus::Mastered);
+
+    // A miss resets the streak
+    mark(&mut set, false);
+    assert_eq!(set.cards[0].streak, 0);
+    mark(&mut set, true);
+    assert_eq!(queue(&set), [0]);
+
+    // Mastering the last card of a round starts the next, shorter one
+    assert!(matches!(mark(&mut set, true), Answer::Correct));
+    assert_eq!(set.current_section, 1);
+    assert_eq!(queue(&set), [4, 5]);
+
+    // The gap is cut short when there aren't enough cards left
+    mark(&mut set, false);
+    assert_eq!(queue(&set), [5, 4]);
+    for _ in 0..3 {
+        assert!(matches!(mark(&mut set, true), Answer::Correct));
+    }
+    assert_eq!(queue(&set), [4]);
+    assert!(!set.is_finished());
+    assert!(matches!(mark(&mut set, true), Answer::Finished));
+    assert!(set.is_finished());
+
+    let summary = set.summary();
+    assert_eq!(summary.cards, 6);
+    assert_eq!(summary.first_try, 4);
+    assert_eq!(summary.answers, 16);
+}
+
+#[test]
+fn test_small_decks() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let answers = ["a", "b", "b", "a", "c"];
+
+    let mut options = distractor::options("a", answers.into_iter(), &mut rng);
+    options.sort();
+    assert_eq!(options, ["a", "b", "c"]);
+
+    let mut options = distractor::options("a", ["a", "b", "b"].into_iter(), &mut rng);
+    options.sort();
+    assert_eq!(options, ["a", "b"]);
+    assert_eq!(distractor::options("a", ["a"].into_iter(), &mut rng), ["a"]);
+
+    // With only two different answers there's a true/false question instead
+    let mut two = set(&["a", "b", "a"], 10);
+    match two.get_question().unwrap() {
+        Question::TrueFalse(shown) => assert!(shown == "a" || shown == "b"),
+        Question::Choice(_) => panic!("Expected a true/false question"),
+    }
+
+    let mut three = set(&["a", "b", "c"], 10);
+    match three.get_question().unwrap() {
+        Question::Choice(options) => assert_eq!(options.len(), 3),
+        Question::TrueFalse(_) => panic!("Expected a multiple choice question"),
+    }
+}
+
+#[test]
+fn test_tags_and_reverse() {
+    let tagged = |question: &str, answer: &str, tags: &[&str]| {
+        Card::new(question, answer).with_tags(tags.iter().map(|x| x.to_string()).collect())
+    };
+    let cards = vec![
+        tagged("to love", "amo", &["verb"]),
+        tagged("friend", "amicus", &["noun"]),
+        tagged("to see", "video", &["Verb", "latin"]),
+        tagged("field", "ager", &[]),
+        tagged("to be", "sum", &["VERB"]),
+    ];
+    let options = Options {
+        section_size: 10,
+        distractors: Scope::Set,
+        seed: None,
+        tags: vec!["verb".to_owned()],
+        reverse: true,
+    };
+    let mut set = Set::new("test", cards, options, StdRng::seed_from_u64(0));
+
+    // Only the cards with one of the tags are studied, ignoring case
+    let studied = set.studied_cards().map(|x| x.question.as_str());
+    assert_eq!(studied.collect::<Vec<_>>(), ["amo", "video", "sum"]);
+    let mut due = set.sections.concat();
+    due.sort();
+    assert_eq!(due, [0, 2, 4]);
+
+    // Reversing swaps the question and answer, and with it the key progress is saved under
+    assert_eq!(set.cards[0].answer, "to love");
+    assert_eq!(set.cards[0].key(), Card::new("amo", "to love").key());
+    assert_ne!(set.cards[0].key(), Card::new("to love", "amo").key());
+
+    // The counts only include the studied cards
+    let now = schedule::now();
+    for _ in 0..4 {
+        set.cards[0].schedule.review(5, now);
+        set.cards[1].schedule.review(5, now);
+    }
+    set.cards[2].schedule.review(5, now);
+    let counts = set.counts(now);
+    assert_eq!((counts.cards, counts.due, counts.mastered), (3, 1, 1));
+}
+
+#[test]
+fn test_quiz_sections() {
+    let cards = |answers: &[&str]| {
+        answers
+            .iter()
+            .enumerate()
+            .map(|(i, x)| Card::new(&format!("Question {i}"), x))
+            .collect::<Vec<_>>()
+    };
+    let sections = |test: &Test| {
+        let sections = test.sections.iter().map(|x| (x.heading(), x.len()));
+        sections.collect::<Vec<_>>()
+    };
+
+    // Every section gets a question before any gets a second
+    let many = cards(&["a", "b", "c", "d", "e", "f", "g", "h"]);
+    for (questions, sizes) in [(4, [1, 1, 1, 1]), (5, [2, 1, 1, 1]), (7, [2, 2, 2, 1])] {
+        let test = Test::generate("Test", &many, questions, 0);
+        let expected = ["Matching", "Multiple Choice", "True or False", "Written"];
+        assert_eq!(
+            sections(&test),
+            expected.into_iter().zip(sizes).collect::<Vec<_>>()
+        );
+    }
+    assert_eq!(sections(&Test::generate("Test", &many, 2, 0)).len(), 2);
+
+    // With only two different answers multiple choice falls back to true/false
+    let two = cards(&["a", "b", "a", "b", "a"]);
+    let test = Test::generate("Test", &two, 5, 0);
+    assert_eq!(
+        sections(&test),
+        [("Matching", 2), ("True or False", 2), ("Written", 1)]
+    );
+
+    // The matching answer key points at each question's own answer
+    for seed in 0..10 {
+        let test = Test::generate("Test", &many, 8, seed);
+        let Section::Matching { questions, answers } = &test.sections[0] else {
+            panic!("Expected a matching section first");
+        };
+        for (question, answer) in questions {
+            let card = many.iter().find(|x| x.question == *question).unwrap();
+            assert_eq!(answers[*answer], card.answer);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::{sync::Mutex, time::Duration};
+
+use afire::{Content, Method, Response, Server, Status};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use study::{
+    grade::{self, Diff, Grade},
+    set::{Answer, Mode, Question, Set},
+};
+
+const INDEX: &str = include_str!("index.html");
+
+/// Everyone connected studies the same session, so this is shared between every request.
+pub struct App {
+    set: Mutex<Set>,
+    delimiter: String,
+}
+
+impl App {
+    pub fn new(set: Set, delimiter: &str) -> Self {
+        Self {
+            set: Mutex::new(set),
+            delimiter: delimiter.to_owned(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct GradeRequest {
+    key: String,
+    input: String,
+}
+
+#[derive(Deserialize)]
+struct MarkRequest {
+    key: String,
+    correct: bool,
+    latency: u64,
+    mode: Mode,
+}
+
+/// Serves the browser front end until the process is killed.
+/// The page only talks to the engine through a small JSON api:
+/// `GET /api/card` for the current card, `POST /api/grade` to grade a typed answer
+/// and `POST /api/mark` to record an answer and move on.
+/// Grades and marks carry the key of the card they're for, so ones for a card that was
+/// already answered (by another tab, or a double click) are rejected instead of marking the next card.
+pub fn serve(set: Set, host: &str, port: u16, delimiter: &str) {
+    let app = App::new(set, delimiter);
+    let mut server = Server::new(host, port).state(app);
+
+    server.route(Method::GET, "/", |_req| {
+        Response::new().content(Content::HTML).text(INDEX)
+    });
+
+    server.stateful_route(Method::GET, "/api/card", |app, _req| {
+        let mut set = app.set.lock().unwrap();
+        json_response(card(&mut set))
+    });
+
+    server.stateful_route(Method::POST, "/api/grade", |app, req| {
+        grade_answer(&app, &req.body)
+    });
+    server.stateful_route(Method::POST, "/api/mark", |app, req| {
+        mark_answer(&app, &req.body)
+    });
+
+    println!("Serving on http://{host}:{port}");
+    server.start_threaded(4).unwrap();
+}
+
+/// Grades a typed answer to the current card, without marking it.
+pub fn grade_answer(app: &App, body: &[u8]) -> Response {
+    let Ok(body) = serde_json::from_slice::<GradeRequest>(body) else {
+        return bad_request();
+    };
+
+    let set = app.set.lock().unwrap();
+    let Some(card) = set.get_current_card().filter(|x| x.key() == body.key) else {
+        return stale();
+    };
+
+    let answer = &card.answer;
+    let (grade, expected) = match grade::grade(&body.input, answer, &app.delimiter) {
+        Grade::Correct => ("correct", answer.to_owned()),
+        Grade::Close(expected) => ("close", expected),
+        Grade::Wrong(expected) => ("wrong", expected),
+    };
+    let diff = grade::diff(&body.input, &expected)
+        .into_iter()
+        .map(|x| match x {
+            Diff::Same(chr) => json!(["same", chr]),
+            Diff::Missing(chr) => json!(["missing", chr]),
+            Diff::Extra(chr) => json!(["extra", chr]),
+        })
+        .collect::<Vec<_>>();
+
+    json_response(json!({ "grade": grade, "expected": expected, "diff": diff }))
+}
+
+/// Records an answer to the current card and saves the progress.
+pub fn mark_answer(app: &App, body: &[u8]) -> Response {
+    let Ok(body) = serde_json::from_slice::<MarkRequest>(body) else {
+        return bad_request();
+    };
+
+    let mut set = app.set.lock().unwrap();
+    if !is_current(&set, &body.key) {
+        return stale();
+    }
+
+    let answer = set.mark(body.correct, Duration::from_millis(body.latency), body.mode);
+    if let Err(e) = set.save() {
+        return Response::new()
+            .status(Status::InternalServerError)
+            .text(format!("Error saving progress: {e}"));
+    }
+    json_response(json!({
+        "result": match answer {
+            Answer::Correct => "correct",
+            Answer::Incorrect => "incorrect",
+            Answer::Finished => "finished",
+        }
+    }))
+}
+
+/// The current card and a multiple choice question for it, or a summary if the session is over.
+fn card(set: &mut Set) -> Value {
+    let question = set.get_question();
+    let (Some(question), Some(card)) = (question, set.get_current_card()) else {
+        let summary = set.summary();
+        return json!({
+            "name": set.name,
+            "finished": true,
+            "summary": {
+                "cards": summary.cards,
+                "first_try": summary.first_try,
+                "answers": summary.answers,
+            },
+        });
+    };
+
+    let (options, shown) = match question {
+        Question::Choice(options) => (Some(options), None),
+        Question::TrueFalse(shown) => (None, Some(shown)),
+    };
+
+    json!({
+        "name": set.name,
+        "finished": false,
+        "section": set.current_section + 1,
+        "sections": set.sections.len(),
+        "mastered": set.mastered(),
+        "round": set.round_size(),
+        "left": set.queue.len(),
+        "key": card.key(),
+        "question": card.question,
+        "answer": card.answer,
+        "status": card.status.to_string(),
+        "options": options,
+        "shown": shown,
+    })
+}
+
+fn json_response(value: Value) -> Response {
+    Response::new().content(Content::JSON).text(value)
+}
+
+/// If the session is still going and `key` is the card being asked.
+fn is_current(set: &Set, key: &str) -> bool {
+    set.get_current_card().is_some_and(|x| x.key() == key)
+}
+
+fn stale() -> Response {
+    Response::new()
+        .status(Status::Conflict)
+        .text("That card was already answered")
+}
+
+fn bad_request() -> Response {
+    Response::new()
+        .status(Status::BadRequest)
+        .text("Bad request")
+}
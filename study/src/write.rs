@@ -0,0 +1,120 @@
+use std::{
+    io::{Stdout, Write},
+    str,
+    time::Instant,
+};
+
+use crossterm::{
+    cursor::MoveTo,
+    execute, queue,
+    style::{Print, PrintStyledContent, Stylize},
+    terminal::{Clear, ClearType},
+};
+use getch::Getch;
+
+use study::{
+    grade::{self, Diff, Grade},
+    set::{Answer, Mode, Set},
+};
+
+use crate::header;
+
+/// Ctrl+D, escape gets eaten by `getch` as the start of an escape sequence
+const QUIT: u8 = 4;
+const BACKSPACE: [u8; 2] = [8, 127];
+
+/// Runs a session where the answers are typed out.
+/// Returns true if the session was finished, or false if the user quit.
+pub fn write(set: &mut Set, delimiter: &str, getch: &Getch, stdout: &mut Stdout) -> bool {
+    loop {
+        let Some(answer) = set.get_current_card().map(|x| x.answer.clone()) else {
+            return true;
+        };
+        let shown = Instant::now();
+        let Some(input) = read_answer(set, getch, stdout) else {
+            set.save().unwrap();
+            return false;
+        };
+
+        let latency = shown.elapsed();
+        let correct = match grade::grade(&input, &answer, delimiter) {
+            Grade::Correct => true,
+            Grade::Close(expected) => {
+                show_diff(&input, &expected, "Close enough!", stdout);
+                queue!(stdout, Print("\n[ANY KEY TO CONTINUE]")).unwrap();
+                stdout.flush().unwrap();
+                getch.getch().unwrap();
+                true
+            }
+            Grade::Wrong(expected) => {
+                show_diff(&input, &expected, "Incorrect", stdout);
+                queue!(
+                    stdout,
+                    Print(format!(
+                        "\nThe correct answer was {answer}\n[O] I was right\t[ANY KEY TO CONTINUE]"
+                    ))
+                )
+                .unwrap();
+                stdout.flush().unwrap();
+                getch.getch().unwrap() as char == 'o'
+            }
+        };
+
+        let marked = set.mark(correct, latency, Mode::Write);
+        set.save().unwrap();
+        if let Answer::Finished = marked {
+            return true;
+        }
+    }
+}
+
+/// Lets the user type out an answer, returns `None` if they pressed Ctrl+D to quit.
+fn read_answer(set: &Set, getch: &Getch, stdout: &mut Stdout) -> Option<String> {
+    let mut input = String::new();
+    // Bytes of a multi-byte character that hasn't been fully typed yet
+    let mut pending = Vec::new();
+
+    loop {
+        header(set, "[^D] Quit", stdout);
+        queue!(stdout, Print(format!("= {input}"))).unwrap();
+        stdout.flush().unwrap();
+
+        match getch.getch().unwrap() {
+            QUIT => return None,
+            b'\r' | b'\n' if !input.trim().is_empty() => return Some(input),
+            x if BACKSPACE.contains(&x) => {
+                input.pop();
+            }
+            x if x.is_ascii_control() => {}
+            x if x.is_ascii() => input.push(x as char),
+            x => {
+                pending.push(x);
+                if let Ok(chr) = str::from_utf8(&pending) {
+                    input.push_str(chr);
+                    pending.clear();
+                }
+            }
+        }
+    }
+}
+
+/// Shows what was typed with the missing characters in green and the extra ones crossed out in red.
+fn show_diff(input: &str, expected: &str, message: &str, stdout: &mut Stdout) {
+    execute!(
+        stdout,
+        Clear(ClearType::All),
+        MoveTo(0, 0),
+        Print(format!("{message}\n\n"))
+    )
+    .unwrap();
+
+    for diff in grade::diff(input, expected) {
+        match diff {
+            Diff::Same(chr) => queue!(stdout, Print(chr)),
+            Diff::Missing(chr) => queue!(stdout, PrintStyledContent(chr.green().underlined())),
+            Diff::Extra(chr) => queue!(stdout, PrintStyledContent(chr.red().crossed_out())),
+        }
+        .unwrap();
+    }
+    queue!(stdout, Print("\n")).unwrap();
+}